@@ -0,0 +1,24 @@
+//times perft on the start position and kiwipete, run it with cargo bench
+//the fastest of a few runs is printed so other programs running at the same time matter less
+
+use std::time::{Duration, Instant};
+
+use linug_chess::{perft, Position};
+
+const RUNS: usize = 3;
+
+fn time_perft(name: &str, fen: &str, depth: u8, nodes: usize) {
+    let pos = Position::from_fen(fen).unwrap();
+    let mut fastest = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        assert_eq!(perft(&pos, depth), nodes);
+        fastest = fastest.min(start.elapsed());
+    }
+    println!("{} depth {}: {} nodes in {:.3}s, {:.1} Mnps", name, depth, nodes, fastest.as_secs_f64(), nodes as f64 / fastest.as_secs_f64() / 1e6);
+}
+
+fn main() {
+    time_perft("startpos", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 6, 119060324);
+    time_perft("kiwipete", "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 5, 193690690);
+}
@@ -0,0 +1,86 @@
+//queries about which squares are attacked and by what, for code using the library
+//the engine itself uses the u64 functions in lib.rs, these wrap them with typed squares and bitboards
+
+use crate::{attackers, piece_attacks, pinned_pieces, Bitboard, Color, Piece, Position, Role, Square, PIECES};
+
+impl Position {
+    //every piece of the color that attacks the square, the square does not have to be empty
+    pub fn attackers_to(&self, square: Square, color: Color) -> Bitboard {
+        Bitboard(attackers(self, color == Color::White, self.w_all | self.b_all, square.index()))
+    }
+
+    pub fn is_attacked(&self, square: Square, color: Color) -> bool {
+        !self.attackers_to(square, color).is_empty()
+    }
+
+    //the pieces giving check to the side to move
+    pub fn checkers(&self) -> Bitboard {
+        let king = self.by_piece(Piece {color: self.turn(), role: Role::King});
+        match king.first() {
+            Some(king) => self.attackers_to(king, !self.turn()),
+            None => Bitboard::EMPTY
+        }
+    }
+
+    pub fn is_check(&self) -> bool {
+        !self.checkers().is_empty()
+    }
+
+    //the pieces of the side to move that can not leave the line between their king and an opponent slider
+    pub fn pinned(&self) -> Bitboard {
+        Bitboard(pinned_pieces(self, self.w_turn))
+    }
+
+    //the squares attacked by the piece on the square, empty if there is no piece
+    //sliders stop at the first piece in each direction, that square is included whatever its color
+    pub fn attacks_from(&self, square: Square) -> Bitboard {
+        match self.piece_at(square) {
+            Some(piece) => Bitboard(piece_attacks(piece.role, piece.color == Color::White, square.index(), self.w_all | self.b_all)),
+            None => Bitboard::EMPTY
+        }
+    }
+
+    //every square attacked by at least one piece of the color
+    pub fn attacked_squares(&self, color: Color) -> Bitboard {
+        let mut attacked = Bitboard::EMPTY;
+        for role in PIECES {
+            for square in self.by_piece(Piece {color, role}) {
+                attacked |= self.attacks_from(square);
+            }
+        }
+        attacked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bitboard, Color, Position, Square};
+
+    fn squares(names: &[&str]) -> Bitboard {
+        names.iter().map(|name| Square::from_name(name).unwrap()).collect()
+    }
+
+    #[test]
+    fn attacks() {
+        let pos = Position::from_fen("4k3/8/8/8/1b6/8/3NP3/r3K2R w K - 0 1").unwrap();
+        let e1 = Square::from_name("e1").unwrap();
+        assert_eq!(pos.checkers(), squares(&["a1"]));
+        assert!(pos.is_check());
+        assert!(pos.is_attacked(e1, Color::Black));
+        assert_eq!(pos.attackers_to(Square::from_name("c5").unwrap(), Color::White), Bitboard::EMPTY);
+        assert_eq!(pos.attackers_to(Square::from_name("c4").unwrap(), Color::White), squares(&["d2"]));
+        assert_eq!(pos.attackers_to(Square::from_name("f2").unwrap(), Color::White), squares(&["e1"]));
+        assert_eq!(pos.attacks_from(Square::from_name("e2").unwrap()), squares(&["d3", "f3"]));
+        assert_eq!(pos.attacks_from(Square::from_name("h1").unwrap()), squares(&["h2", "h3", "h4", "h5", "h6", "h7", "h8", "g1", "f1", "e1"]));
+        assert_eq!(pos.attacks_from(Square::from_name("e4").unwrap()), Bitboard::EMPTY);
+        //the bishop on b4 pins the knight on d2 to the king
+        assert_eq!(pos.pinned(), squares(&["d2"]));
+        assert!(pos.attacked_squares(Color::Black).contains(Square::from_name("d2").unwrap()));
+        assert!(!pos.attacked_squares(Color::White).contains(Square::from_name("e8").unwrap()));
+
+        let pos = Position::startpos();
+        assert_eq!(pos.checkers(), Bitboard::EMPTY);
+        assert_eq!(pos.pinned(), Bitboard::EMPTY);
+        assert_eq!(pos.attacked_squares(Color::White).count(), 22);
+    }
+}
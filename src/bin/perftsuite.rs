@@ -0,0 +1,48 @@
+//runs a perft suite like perftsuite.epd and prints every node count that does not match
+//usage: perftsuite <file> [max depth]
+//https://www.chessprogramming.org/Perft_Results
+
+use std::process::ExitCode;
+use std::time::Instant;
+
+use linug_chess::perft::{parse_epd, run_suite};
+
+const DEFAULT_MAX_DEPTH: u8 = 6;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = args.get(1) else {
+        eprintln!("usage: perftsuite <file> [max depth]");
+        return ExitCode::FAILURE
+    };
+    let max_depth = match args.get(2).map(|depth| depth.parse::<u8>()) {
+        None => DEFAULT_MAX_DEPTH,
+        Some(Ok(depth)) => depth,
+        Some(Err(_)) => {
+            eprintln!("max depth has to be a number");
+            return ExitCode::FAILURE
+        }
+    };
+    let epd = match std::fs::read_to_string(path) {
+        Ok(epd) => epd,
+        Err(error) => {
+            eprintln!("could not read {}: {}", path, error);
+            return ExitCode::FAILURE
+        }
+    };
+    let entries = match parse_epd(&epd) {
+        Ok(entries) => entries,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE
+        }
+    };
+
+    let start = Instant::now();
+    let mismatches = run_suite(&entries, max_depth);
+    for mismatch in &mismatches {
+        println!("{}", mismatch);
+    }
+    println!("{} positions up to depth {}, {} mismatches in {:.1}s", entries.len(), max_depth, mismatches.len(), start.elapsed().as_secs_f64());
+    if mismatches.is_empty() {ExitCode::SUCCESS} else {ExitCode::FAILURE}
+}
@@ -0,0 +1,257 @@
+//uci frontend so the engine can be loaded by chess guis like cute chess
+//https://www.wbec-ridderkerk.nl/html/UCIProtocol.html
+
+use std::io::{self, BufRead};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use linug_chess::Position;
+use linug_chess::history::History;
+use linug_chess::perft::perft_divide;
+use linug_chess::search::{Score, SearchLimits, SearchResult};
+use linug_chess::transposition::{DEFAULT_SIZE_MB, TranspositionTable};
+
+const MAX_HASH_MB: usize = 4096;
+
+//the parameters of the go command, times are in milliseconds
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct GoLimits {
+    depth: Option<u8>,
+    movetime: Option<u64>,
+    wtime: Option<u64>,
+    btime: Option<u64>,
+    winc: Option<u64>,
+    binc: Option<u64>,
+    movestogo: Option<u64>,
+    infinite: bool,
+}
+
+fn main() {
+    let mut position = History::new(Position::startpos());
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut search: Option<JoinHandle<()>> = None;
+    let mut table = Arc::new(TranspositionTable::new(DEFAULT_SIZE_MB));
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"uci") => {
+                println!("id name linug-chess");
+                println!("id author linug");
+                println!("option name Hash type spin default {} min 1 max {}", DEFAULT_SIZE_MB, MAX_HASH_MB);
+                println!("uciok");
+            },
+            Some(&"isready") => println!("readyok"),
+            Some(&"setoption") => {
+                stop_search(&stop, &mut search);
+                match parse_setoption(&tokens[1..]) {
+                    //option names are not case sensitive
+                    Some((name, value)) if name.eq_ignore_ascii_case("hash") => match value.parse::<usize>() {
+                        Ok(size_mb) => table = Arc::new(TranspositionTable::new(size_mb.clamp(1, MAX_HASH_MB))),
+                        Err(_) => println!("info string invalid Hash value {}", value)
+                    },
+                    _ => println!("info string unknown option")
+                }
+            },
+            Some(&"ucinewgame") => {
+                stop_search(&stop, &mut search);
+                position = History::new(Position::startpos());
+                table.clear();
+            },
+            Some(&"position") => {
+                stop_search(&stop, &mut search);
+                match parse_position(&tokens[1..]) {
+                    Ok(new_position) => position = new_position,
+                    Err(error) => println!("info string {}", error)
+                }
+            },
+            //go perft prints the node count after every move like stockfish does, used to find move generation bugs
+            Some(&"go") if tokens.get(1) == Some(&"perft") => {
+                stop_search(&stop, &mut search);
+                match tokens.get(2).and_then(|depth| depth.parse::<u8>().ok()).filter(|depth| *depth > 0) {
+                    Some(depth) => {
+                        let divide = perft_divide(&position, depth);
+                        for (m, count) in &divide {
+                            println!("{}: {}", m, count);
+                        }
+                        println!();
+                        println!("Nodes searched: {}", divide.iter().map(|(_, count)| count).sum::<usize>());
+                    },
+                    None => println!("info string go perft needs a depth of at least 1")
+                }
+            },
+            Some(&"go") => {
+                stop_search(&stop, &mut search);
+                stop.store(false, Ordering::SeqCst);
+                let go = parse_go(&tokens[1..]);
+                let limits = search_limits(&go, position.white_to_move(), stop.clone());
+                let position = position.clone();
+                let stop = stop.clone();
+                let table = table.clone();
+                search = Some(thread::spawn(move || {
+                    let result = position.search_with_table(limits, &table, |result| print_info(result, table.hashfull()));
+                    //with go infinite the best move may only be sent after stop
+                    while go.infinite && !stop.load(Ordering::SeqCst) {
+                        thread::sleep(Duration::from_millis(5));
+                    }
+                    println!("bestmove {}", result.best_move.map_or("0000".to_string(), |m| m.to_uci()));
+                }));
+            },
+            Some(&"stop") => stop_search(&stop, &mut search),
+            Some(&"quit") => {
+                stop_search(&stop, &mut search);
+                break;
+            },
+            _ => {} //unknown commands are ignored as the protocol says
+        }
+    }
+}
+
+//stops the running search and waits for it to send its best move
+fn stop_search(stop: &AtomicBool, search: &mut Option<JoinHandle<()>>) {
+    stop.store(true, Ordering::SeqCst);
+    if let Some(handle) = search.take() {
+        let _ = handle.join();
+    }
+}
+
+//parses the arguments of the position command
+//example startpos moves e2e4 e7e5 or fen rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 moves e7e5
+//the moves are kept in a History so the search knows which positions would be repetitions
+fn parse_position(tokens: &[&str]) -> Result<History, String> {
+    let moves_index = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+    let mut position = History::new(match tokens.first() {
+        Some(&"startpos") => Position::startpos(),
+        Some(&"fen") => Position::from_fen(&tokens[1..moves_index].join(" ")).map_err(|error| format!("invalid fen: {}", error))?,
+        _ => return Err("position must be followed by startpos or fen".to_string())
+    });
+    for m in tokens.iter().skip(moves_index + 1) {
+        position.try_make_move(m).map_err(|error| format!("invalid move {}: {}", m, error))?;
+    }
+    Ok(position)
+}
+
+//parses the arguments of the setoption command, example name Hash value 64
+//option names can contain spaces so everything between name and value is the name
+fn parse_setoption<'a>(tokens: &[&'a str]) -> Option<(String, &'a str)> {
+    if tokens.first() != Some(&"name") {
+        return None
+    }
+    let value_index = tokens.iter().position(|token| *token == "value").unwrap_or(tokens.len());
+    Some((tokens[1..value_index].join(" "), tokens.get(value_index + 1).copied().unwrap_or("")))
+}
+
+//parses the arguments of the go command, unknown arguments are ignored
+fn parse_go(tokens: &[&str]) -> GoLimits {
+    let mut limits = GoLimits::default();
+    let mut i = 0;
+    while i < tokens.len() {
+        let value = tokens.get(i + 1).and_then(|value| value.parse::<u64>().ok());
+        match tokens[i] {
+            "depth" => limits.depth = value.map(|depth| depth.min(u8::MAX as u64) as u8),
+            "movetime" => limits.movetime = value,
+            "wtime" => limits.wtime = value,
+            "btime" => limits.btime = value,
+            "winc" => limits.winc = value,
+            "binc" => limits.binc = value,
+            "movestogo" => limits.movestogo = value,
+            "infinite" => {
+                limits.infinite = true;
+                i += 1;
+                continue;
+            },
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+    limits
+}
+
+//turns the go command into search limits
+//with a clock the engine uses its share of the remaining time plus most of the increment
+fn search_limits(go: &GoLimits, white_to_move: bool, stop: Arc<AtomicBool>) -> SearchLimits {
+    let mut limits = SearchLimits {depth: go.depth, stop: Some(stop), ..Default::default()};
+    if go.infinite {
+        return limits
+    }
+    let (time, inc) = if white_to_move {(go.wtime, go.winc)} else {(go.btime, go.binc)};
+    if let Some(movetime) = go.movetime {
+        limits.movetime = Some(Duration::from_millis(movetime));
+    }
+    else if let Some(time) = time {
+        let share = time / go.movestogo.unwrap_or(30).max(1) + inc.unwrap_or(0) * 3 / 4;
+        //leave some time so the engine never loses on time because of overhead
+        let movetime = share.min(time.saturating_sub(50)).max(1);
+        limits.movetime = Some(Duration::from_millis(movetime));
+    }
+    limits
+}
+
+fn print_info(result: &SearchResult, hashfull: u32) {
+    let score = match result.score {
+        Score::Centipawns(cp) => format!("cp {}", cp),
+        Score::Mate(moves) => format!("mate {}", moves)
+    };
+    println!("info depth {} score {} nodes {} hashfull {} pv {}", result.depth, score, result.nodes, hashfull, result.pv.iter().map(|m| m.to_uci()).collect::<Vec<_>>().join(" "));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    use super::{GoLimits, parse_go, parse_position, parse_setoption, search_limits};
+
+    #[test]
+    fn position_command() {
+        assert_eq!(parse_position(&["startpos", "moves", "e2e4", "e7e5"]).unwrap().to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+        let fen = "fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1g1".split(' ').collect::<Vec<&str>>();
+        assert_eq!(parse_position(&fen).unwrap().to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        assert!(parse_position(&["startpos", "moves", "e2e5"]).is_err());
+        assert!(parse_position(&["fen", "8/8/8", "w"]).is_err());
+        assert!(parse_position(&[]).is_err());
+        //a position where the king could be captured is rejected instead of crashing the search
+        let fen = "fen 4k3/4R3/8/8/8/8/8/4K3 w - - 0 1 moves e7e8".split(' ').collect::<Vec<&str>>();
+        assert!(parse_position(&fen).is_err());
+    }
+
+    #[test]
+    fn go_command() {
+        assert_eq!(parse_go(&["depth", "5"]), GoLimits {depth: Some(5), ..Default::default()});
+        assert_eq!(parse_go(&["wtime", "1000", "btime", "2000", "winc", "10", "binc", "20", "movestogo", "30"]),
+            GoLimits {wtime: Some(1000), btime: Some(2000), winc: Some(10), binc: Some(20), movestogo: Some(30), ..Default::default()});
+        assert_eq!(parse_go(&["infinite"]), GoLimits {infinite: true, ..Default::default()});
+        assert_eq!(parse_go(&["ponder", "movetime", "100"]), GoLimits {movetime: Some(100), ..Default::default()});
+    }
+
+    #[test]
+    fn setoption_command() {
+        assert_eq!(parse_setoption(&["name", "Hash", "value", "64"]), Some(("Hash".to_string(), "64")));
+        assert_eq!(parse_setoption(&["name", "Clear", "Hash"]), Some(("Clear Hash".to_string(), "")));
+        assert_eq!(parse_setoption(&["Hash", "64"]), None);
+    }
+
+    #[test]
+    fn time_management() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let limits = search_limits(&parse_go(&["movetime", "100"]), true, stop.clone());
+        assert_eq!(limits.movetime, Some(Duration::from_millis(100)));
+        let go = parse_go(&["wtime", "30000", "btime", "60000", "winc", "1000", "binc", "0"]);
+        assert_eq!(search_limits(&go, true, stop.clone()).movetime, Some(Duration::from_millis(1750)));
+        assert_eq!(search_limits(&go, false, stop.clone()).movetime, Some(Duration::from_millis(2000)));
+        let limits = search_limits(&parse_go(&["infinite"]), true, stop.clone());
+        assert_eq!(limits.movetime, None);
+        assert_eq!(search_limits(&parse_go(&["depth", "4"]), true, stop).depth, Some(4));
+    }
+}
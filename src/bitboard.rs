@@ -0,0 +1,329 @@
+//a set of squares stored as the bits of a u64, the same layout the engine uses internally
+//bit 0 is h1, bit 7 is a1 and bit 63 is a8, see SQUARE_NAME
+//https://www.chessprogramming.org/Bitboards
+
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+use crate::{File, Rank, Square};
+
+pub(crate) const FILE: [u64; 8] = [
+    0b1000000010000000100000001000000010000000100000001000000010000000u64,
+    0b0100000001000000010000000100000001000000010000000100000001000000u64,
+    0b0010000000100000001000000010000000100000001000000010000000100000u64,
+    0b0001000000010000000100000001000000010000000100000001000000010000u64,
+    0b0000100000001000000010000000100000001000000010000000100000001000u64,
+    0b0000010000000100000001000000010000000100000001000000010000000100u64,
+    0b0000001000000010000000100000001000000010000000100000001000000010u64,
+    0b0000000100000001000000010000000100000001000000010000000100000001u64,
+];
+
+pub(crate) const RANK: [u64; 8] = [
+    0b11111111u64,
+    0b11111111u64 << 8,
+    0b11111111u64 << 16,
+    0b11111111u64 << 24,
+    0b11111111u64 << 32,
+    0b11111111u64 << 40,
+    0b11111111u64 << 48,
+    0b11111111u64 << 56,
+];
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+    pub const FULL: Bitboard = Bitboard(!0);
+
+    //indexed by File.index() and Rank.index()
+    pub const FILES: [Bitboard; 8] = {
+        let mut files = [Bitboard::EMPTY; 8];
+        let mut file = 0;
+        while file < 8 {
+            files[file] = Bitboard(FILE[file]);
+            file += 1;
+        }
+        files
+    };
+    pub const RANKS: [Bitboard; 8] = {
+        let mut ranks = [Bitboard::EMPTY; 8];
+        let mut rank = 0;
+        while rank < 8 {
+            ranks[rank] = Bitboard(RANK[rank]);
+            rank += 1;
+        }
+        ranks
+    };
+
+    pub const DIAGONAL: Bitboard = Bitboard(0x0102040810204080); //a1 to h8
+    pub const ANTI_DIAGONAL: Bitboard = Bitboard(0x8040201008040201); //a8 to h1
+    pub const LIGHT_SQUARES: Bitboard = Bitboard(0xaa55aa55aa55aa55);
+    pub const DARK_SQUARES: Bitboard = Bitboard(0x55aa55aa55aa55aa);
+
+    pub fn file(file: File) -> Bitboard {
+        Bitboard::FILES[file.index() as usize]
+    }
+
+    pub fn rank(rank: Rank) -> Bitboard {
+        Bitboard::RANKS[rank.index() as usize]
+    }
+
+    //the diagonal going up towards the h-file through the square
+    pub fn diagonal(square: Square) -> Bitboard {
+        let shift = square.rank().index() as i32 - square.file().index() as i32;
+        if shift >= 0 {
+            Bitboard(Bitboard::DIAGONAL.0 << (8 * shift))
+        }
+        else {
+            Bitboard(Bitboard::DIAGONAL.0 >> (-8 * shift))
+        }
+    }
+
+    //the diagonal going up towards the a-file through the square
+    pub fn anti_diagonal(square: Square) -> Bitboard {
+        let shift = square.rank().index() as i32 + square.file().index() as i32 - 7;
+        if shift >= 0 {
+            Bitboard(Bitboard::ANTI_DIAGONAL.0 << (8 * shift))
+        }
+        else {
+            Bitboard(Bitboard::ANTI_DIAGONAL.0 >> (-8 * shift))
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn contains(self, square: Square) -> bool {
+        self.0 & square.bitboard() != 0
+    }
+
+    pub fn insert(&mut self, square: Square) {
+        self.0 |= square.bitboard();
+    }
+
+    pub fn remove(&mut self, square: Square) {
+        self.0 &= !square.bitboard();
+    }
+
+    //the square with the lowest index, h1 comes first and a8 last
+    pub fn first(self) -> Option<Square> {
+        if self.is_empty() {None} else {Some(Square::from_bitboard(self.0 & self.0.wrapping_neg()))}
+    }
+
+    //the shifts move every square one step and drop the squares that would leave the board
+    pub fn north(self) -> Bitboard {
+        Bitboard(self.0 << 8)
+    }
+
+    pub fn south(self) -> Bitboard {
+        Bitboard(self.0 >> 8)
+    }
+
+    //towards the h-file
+    pub fn east(self) -> Bitboard {
+        Bitboard((self.0 & !FILE[7]) >> 1)
+    }
+
+    //towards the a-file
+    pub fn west(self) -> Bitboard {
+        Bitboard((self.0 & !FILE[0]) << 1)
+    }
+
+    pub fn north_east(self) -> Bitboard {
+        Bitboard((self.0 & !FILE[7]) << 7)
+    }
+
+    pub fn north_west(self) -> Bitboard {
+        Bitboard((self.0 & !FILE[0]) << 9)
+    }
+
+    pub fn south_east(self) -> Bitboard {
+        Bitboard((self.0 & !FILE[7]) >> 9)
+    }
+
+    pub fn south_west(self) -> Bitboard {
+        Bitboard((self.0 & !FILE[0]) >> 7)
+    }
+}
+
+//iterating a bitboard gives its squares from h1 to a8
+impl Iterator for Bitboard {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        let square = self.first()?;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.count() as usize, Some(self.count() as usize))
+    }
+}
+
+impl ExactSizeIterator for Bitboard {}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(squares: I) -> Bitboard {
+        let mut bitboard = Bitboard::EMPTY;
+        for square in squares {
+            bitboard.insert(square);
+        }
+        bitboard
+    }
+}
+
+impl From<Square> for Bitboard {
+    fn from(square: Square) -> Bitboard {
+        Bitboard(square.bitboard())
+    }
+}
+
+impl From<u64> for Bitboard {
+    fn from(bitboard: u64) -> Bitboard {
+        Bitboard(bitboard)
+    }
+}
+
+impl From<Bitboard> for u64 {
+    fn from(bitboard: Bitboard) -> u64 {
+        bitboard.0
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+
+    fn bitand(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 & other.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+
+    fn bitor(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 | other.0)
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Bitboard;
+
+    fn bitxor(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 ^ other.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, other: Bitboard) {
+        self.0 &= other.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, other: Bitboard) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, other: Bitboard) {
+        self.0 ^= other.0;
+    }
+}
+
+//prints the board from white's side, 1 for squares in the set and . for the others
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rank in Rank::ALL.into_iter().rev() {
+            write!(f, "{} ", rank.char())?;
+            for file in File::ALL {
+                write!(f, " {}", if self.contains(Square::new(file, rank)) {'1'} else {'.'})?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "  ")?;
+        for file in File::ALL {
+            write!(f, " {}", file.char())?;
+        }
+        writeln!(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bitboard;
+    use crate::{File, Rank, Square};
+
+    fn square(name: &str) -> Square {
+        Square::from_name(name).unwrap()
+    }
+
+    #[test]
+    fn bitboard_squares() {
+        let bitboard: Bitboard = ["e4", "a1", "h8"].into_iter().map(square).collect();
+        assert_eq!(bitboard.count(), 3);
+        assert!(bitboard.contains(square("e4")) && !bitboard.contains(square("e5")));
+        assert_eq!(bitboard.first(), Some(square("a1")));
+        assert_eq!(bitboard.map(|square| square.name()).collect::<Vec<_>>(), ["a1", "e4", "h8"]);
+        assert_eq!(Bitboard::EMPTY.first(), None);
+
+        assert_eq!(Bitboard::file(File::E) & Bitboard::rank(Rank::Fourth), square("e4").into());
+        assert_eq!(Bitboard::diagonal(square("e4")), ["b1", "c2", "d3", "e4", "f5", "g6", "h7"].into_iter().map(square).collect());
+        assert_eq!(Bitboard::anti_diagonal(square("e4")), ["b7", "c6", "d5", "e4", "f3", "g2", "h1", "a8"].into_iter().map(square).collect());
+        assert_eq!(Bitboard::diagonal(square("a1")), Bitboard::DIAGONAL);
+        assert_eq!(Bitboard::anti_diagonal(square("h1")), Bitboard::ANTI_DIAGONAL);
+        assert!(Bitboard::DARK_SQUARES.contains(square("a1")) && Bitboard::LIGHT_SQUARES.contains(square("h1")));
+        assert_eq!(!Bitboard::DARK_SQUARES, Bitboard::LIGHT_SQUARES);
+    }
+
+    #[test]
+    fn bitboard_shifts() {
+        let a_file = Bitboard::file(File::A);
+        let h_file = Bitboard::file(File::H);
+        assert_eq!(a_file.west(), Bitboard::EMPTY);
+        assert_eq!(h_file.east(), Bitboard::EMPTY);
+        assert_eq!(a_file.east(), Bitboard::file(File::B));
+        assert_eq!(Bitboard::rank(Rank::Eighth).north(), Bitboard::EMPTY);
+        assert_eq!(Bitboard::rank(Rank::First).south(), Bitboard::EMPTY);
+        let e4 = Bitboard::from(square("e4"));
+        assert_eq!(e4.north_east(), square("f5").into());
+        assert_eq!(e4.north_west(), square("d5").into());
+        assert_eq!(e4.south_east(), square("f3").into());
+        assert_eq!(e4.south_west(), square("d3").into());
+        assert_eq!(a_file.north_west() | a_file.south_west() | h_file.north_east() | h_file.south_east(), Bitboard::EMPTY);
+        assert_eq!(Bitboard::FULL.north().count(), 56);
+        assert_eq!(Bitboard::FULL.north_east().count(), 49);
+    }
+
+    #[test]
+    fn print_bitboard() {
+        let bitboard = Bitboard::rank(Rank::Second) | Bitboard::from(Square::new(File::E, Rank::Fourth));
+        assert_eq!(bitboard.to_string(), "\
+8  . . . . . . . .
+7  . . . . . . . .
+6  . . . . . . . .
+5  . . . . . . . .
+4  . . . . 1 . . .
+3  . . . . . . . .
+2  1 1 1 1 1 1 1 1
+1  . . . . . . . .
+   a b c d e f g h
+");
+    }
+}
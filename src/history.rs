@@ -0,0 +1,217 @@
+//a position together with the moves that led to it
+//Position is Copy and does not know how it was reached, the history keeps the Undo of every move
+//to take them back and to find repetitions, the threefold and fivefold rules are only checked here
+
+use std::ops::Deref;
+
+use crate::{GameResult, Move, MoveError, Outcome, Position, Termination, Undo};
+
+#[derive(Clone, Debug)]
+pub struct History {
+    position: Position, //the current position
+    undos: Vec<Undo>, //one for every move made, oldest first
+}
+
+impl History {
+    pub fn new(position: Position) -> History {
+        History {position, undos: vec![]}
+    }
+
+    //the current position
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    //plays a move in uci format, does not check if the move is legal, see Position.make_move()
+    pub fn make_move(&mut self, m: &str) {
+        self.play(self.position.unchecked_uci_to_move(m));
+    }
+
+    //plays a typed move, does not check if the move is legal, see Position.play()
+    pub fn play(&mut self, m: Move) {
+        let undo = self.position.play(m);
+        self.undos.push(undo);
+    }
+
+    //plays a move in uci format after checking that it is legal, the game also has to be in progress
+    pub fn try_make_move(&mut self, m: &str) -> Result<(), MoveError> {
+        if !self.game_in_progress() {
+            return Err(MoveError::GameOver)
+        }
+        let m = self.position.checked_uci_to_move(m)?;
+        self.play(m);
+        Ok(())
+    }
+
+    //plays a move written in san, returns an error if the move is not valid san or is not legal
+    pub fn try_make_san_move(&mut self, san: &str) -> Result<(), MoveError> {
+        if !self.game_in_progress() {
+            return Err(MoveError::GameOver)
+        }
+        let m = self.position.checked_san_to_move(san)?;
+        self.play(m);
+        Ok(())
+    }
+
+    //takes back the last move made, returns false if there are no moves to take back
+    pub fn unmake_move(&mut self) -> bool {
+        match self.undos.pop() {
+            Some(undo) => {
+                self.position.unmake_move(undo);
+                true
+            }
+            None => false
+        }
+    }
+
+    //the zobrist keys of the positions before the current one, oldest first
+    pub(crate) fn previous_hashes(&self) -> Vec<u64> {
+        self.undos.iter().map(|undo| undo.hash).collect()
+    }
+
+    //how many times the current position has occurred, counting the current one
+    //only positions since the last capture or pawn move can be repeated
+    pub fn repetitions(&self) -> usize {
+        let position = self.position();
+        1 + self.undos.iter().rev()
+            .take(position.halfmove_clock() as usize)
+            .skip(1)
+            .step_by(2)
+            .filter(|undo| undo.hash == position.hash())
+            .count()
+    }
+
+    //like Position.game_in_progress() but the game also ends when the same position has occurred five times
+    pub fn game_in_progress(&self) -> bool {
+        self.position().game_in_progress() && self.repetitions() < 5
+    }
+
+    //the result of the game, should only be used after game_in_progress returns false or after a draw was claimed
+    pub fn get_result(&self) -> GameResult {
+        self.position().get_result()
+    }
+
+    //like Position.outcome() but also ends the game on fivefold repetition
+    pub fn outcome(&self) -> Option<Outcome> {
+        if let Some(outcome) = self.position().outcome() {
+            return Some(outcome)
+        }
+        if self.repetitions() >= 5 {
+            return Some(Outcome {result: GameResult::Draw, termination: Termination::Repetition})
+        }
+        None
+    }
+
+    //like Position.claim_draw() but a draw can also be claimed when the same position has occurred three times
+    pub fn claim_draw(&self) -> Option<Outcome> {
+        if !self.game_in_progress() {
+            return None
+        }
+        if let Some(outcome) = self.position().claim_draw() {
+            return Some(outcome)
+        }
+        if self.repetitions() >= 3 {
+            return Some(Outcome {result: GameResult::Draw, termination: Termination::Repetition})
+        }
+        None
+    }
+
+    pub fn can_claim_draw(&self) -> bool {
+        self.claim_draw().is_some()
+    }
+}
+
+impl From<Position> for History {
+    fn from(position: Position) -> History {
+        History::new(position)
+    }
+}
+
+//every method of the current position that does not make a move can be used on the history directly
+impl Deref for History {
+    type Target = Position;
+
+    fn deref(&self) -> &Position {
+        self.position()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+    use crate::GameResult::*;
+    use crate::Position;
+
+    //makes and unmakes every move in depth and checks that the position is restored
+    fn assert_unmake_restores(pos: &mut History, depth: u8) {
+        if depth == 0 {
+            return
+        }
+        let fen = pos.to_fen();
+        let hash = pos.hash();
+        let legal_moves = pos.get_legal_moves();
+        for m in &legal_moves {
+            pos.make_move(m);
+            assert_unmake_restores(pos, depth - 1);
+            assert!(pos.unmake_move());
+            assert_eq!(pos.to_fen(), fen);
+            assert_eq!(pos.hash(), hash);
+            assert_eq!(&pos.get_legal_moves(), &legal_moves);
+        }
+    }
+
+    #[test]
+    fn unmake_move() {
+        let mut pos = History::new(Position::startpos());
+        assert!(!pos.unmake_move());
+        pos.make_move("e2e4");
+        pos.make_move("e7e5");
+        assert!(pos.unmake_move());
+        assert!(pos.unmake_move());
+        assert_eq!(pos.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+        //covers captures, en passant, castling and promotions for both colors
+        assert_unmake_restores(&mut Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ").unwrap().into(), 2);
+        assert_unmake_restores(&mut Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap().into(), 3);
+        assert_unmake_restores(&mut Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ").unwrap().into(), 3);
+    }
+
+    #[test]
+    fn repetition() {
+        let mut pos = History::new(Position::startpos());
+        assert_eq!(pos.repetitions(), 1);
+        for i in 2..=5 {
+            for m in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                assert!(pos.game_in_progress());
+                pos.make_move(m);
+            }
+            assert_eq!(pos.repetitions(), i);
+            assert_eq!(pos.can_claim_draw(), (3..5).contains(&i));
+        }
+        assert!(!pos.game_in_progress());
+        assert_eq!(pos.get_result(), Draw);
+        assert_eq!(pos.try_make_move("g1f3"), Err(crate::MoveError::GameOver));
+        //the position alone does not know it has been repeated
+        assert!(pos.position().game_in_progress());
+        pos.unmake_move();
+        assert_eq!(pos.repetitions(), 4);
+        assert!(pos.game_in_progress());
+
+        //the side to move and castling rights are part of the position
+        let mut pos = History::new(Position::from_fen("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1").unwrap());
+        for m in ["h1h2", "a8a7", "h2h1", "a7a8"] {
+            pos.make_move(m);
+        }
+        assert_eq!(pos.repetitions(), 1);
+        for m in ["e1f1", "e8f8", "f1e1", "f8e8"] {
+            pos.make_move(m);
+        }
+        assert_eq!(pos.repetitions(), 2);
+
+        //en passant only makes the position different when it can be played
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert_eq!(Position::from_fen(fen).unwrap().hash(), Position::from_fen(&fen.replace("e3", "-")).unwrap().hash());
+        let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert_ne!(Position::from_fen(fen).unwrap().hash(), Position::from_fen(&fen.replace("e3", "-")).unwrap().hash());
+    }
+}
@@ -1,13 +1,98 @@
 use std::collections::HashMap;
+use std::fmt;
 
-use Piece::*;
-use rand::{Rng, thread_rng};
+use Role::*;
+use bitboard::{FILE, RANK};
 use lazy_static::lazy_static;
 use GameResult::*;
 
-#[derive(Copy,Clone, Debug, PartialEq, Eq)]
-enum Piece {
-    King = 0, Queen = 1, Bishop= 2, Knight = 3, Rook = 4, Pawn = 5, Void
+mod attacks;
+mod bitboard;
+pub mod history;
+mod lockless;
+mod move_list;
+pub mod perft;
+pub mod pgn;
+mod san;
+pub mod search;
+mod square;
+pub mod transposition;
+mod zobrist;
+
+pub use bitboard::Bitboard;
+pub use move_list::MoveList;
+pub use square::{File, Rank, Square};
+
+//the kind of a piece, the discriminant is the index of its bitboard in Position
+#[derive(Copy,Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    King = 0, Queen = 1, Bishop= 2, Knight = 3, Rook = 4, Pawn = 5
+}
+
+impl Role {
+    //the lowercase letter used in fen and uci, k q b n r p
+    pub fn char(self) -> char {
+        match self {
+            King => 'k', Queen => 'q', Bishop => 'b', Knight => 'n', Rook => 'r', Pawn => 'p'
+        }
+    }
+
+    //the role of a fen letter of either case
+    pub fn from_char(letter: char) -> Option<Role> {
+        match letter.to_ascii_lowercase() {
+            'k' => Some(King),
+            'q' => Some(Queen),
+            'b' => Some(Bishop),
+            'n' => Some(Knight),
+            'r' => Some(Rook),
+            'p' => Some(Pawn),
+            _ => None
+        }
+    }
+}
+
+#[derive(Copy,Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    White,
+    Black
+}
+
+impl Color {
+    pub fn other(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White
+        }
+    }
+}
+
+impl std::ops::Not for Color {
+    type Output = Color;
+
+    fn not(self) -> Color {
+        self.other()
+    }
+}
+
+#[derive(Copy,Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub color: Color,
+    pub role: Role,
+}
+
+impl Piece {
+    //the fen letter, uppercase for white and lowercase for black
+    pub fn char(self) -> char {
+        match self.color {
+            Color::White => self.role.char().to_ascii_uppercase(),
+            Color::Black => self.role.char()
+        }
+    }
+
+    pub fn from_char(letter: char) -> Option<Piece> {
+        let color = if letter.is_ascii_uppercase() {Color::White} else {Color::Black};
+        Some(Piece {color, role: Role::from_char(letter)?})
+    }
 }
 
 #[derive(Copy,Clone, Debug, PartialEq, Eq)]
@@ -17,9 +102,193 @@ pub enum GameResult {
     Draw
 }
 
-const PIECES: [Piece; 6] = [Pawn, Knight, Bishop, Rook, Queen, King];
+impl GameResult {
+    //the result as it is written in pgn
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WhiteWin => "1-0",
+            BlackWin => "0-1",
+            Draw => "1/2-1/2"
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+//why a game ended
+#[derive(Copy,Clone, Debug, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule, //claimed after 50 moves or automatic after 75 moves
+    Repetition, //claimed after threefold or automatic after fivefold repetition
+    InsufficientMaterial,
+    Resignation,
+    Timeout,
+    Agreement,
+    Adjudication
+}
+
+impl Termination {
+    //the value of the pgn Termination tag, the standard only has a few values so most endings are "normal"
+    pub fn pgn_tag(&self) -> &'static str {
+        match self {
+            Termination::Timeout => "time forfeit",
+            Termination::Adjudication => "adjudication",
+            _ => "normal"
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Termination::Checkmate => write!(f, "checkmate"),
+            Termination::Stalemate => write!(f, "stalemate"),
+            Termination::FiftyMoveRule => write!(f, "fifty-move rule"),
+            Termination::Repetition => write!(f, "repetition"),
+            Termination::InsufficientMaterial => write!(f, "insufficient material"),
+            Termination::Resignation => write!(f, "resignation"),
+            Termination::Timeout => write!(f, "timeout"),
+            Termination::Agreement => write!(f, "agreement"),
+            Termination::Adjudication => write!(f, "adjudication")
+        }
+    }
+}
+
+//the result of a finished game together with the reason it ended
+#[derive(Copy,Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub result: GameResult,
+    pub termination: Termination,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} by {}", self.result, self.termination)
+    }
+}
+
+//the field of a fen string that could not be parsed
+#[derive(Copy,Clone, Debug, PartialEq, Eq)]
+pub enum FenField {
+    PiecePlacement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber
+}
+
+//returned by Position::from_fen when the fen string is invalid
+#[derive(Copy,Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    MissingField(FenField),
+    WrongRankCount(usize), //the amount of ranks found
+    BadRankLength(u8), //the rank that does not have exactly 8 squares
+    UnknownPiece(char),
+    MissingKing{white: bool},
+    TooManyKings{white: bool},
+    PawnOnBackRank,
+    ImpossibleMaterial{white: bool}, //more pawns and promoted pieces than the 8 pawns a side starts with
+    OpponentInCheck, //the side that is not to move is in check, so its king could be captured
+    InvalidSideToMove,
+    InvalidCastling,
+    InvalidEnPassant,
+    ImpossibleEnPassant, //a valid square but no pawn can have just moved past it
+    InvalidHalfmoveClock,
+    InvalidFullmoveNumber
+}
+
+impl FenError {
+    //returns the field of the fen string that caused the error
+    pub fn field(&self) -> FenField {
+        match self {
+            FenError::MissingField(field) => *field,
+            FenError::WrongRankCount(_) | FenError::BadRankLength(_) | FenError::UnknownPiece(_) |
+            FenError::MissingKing{..} | FenError::TooManyKings{..} | FenError::PawnOnBackRank | FenError::ImpossibleMaterial{..} => FenField::PiecePlacement,
+            FenError::InvalidSideToMove | FenError::OpponentInCheck => FenField::SideToMove,
+            FenError::InvalidCastling => FenField::Castling,
+            FenError::InvalidEnPassant | FenError::ImpossibleEnPassant => FenField::EnPassant,
+            FenError::InvalidHalfmoveClock => FenField::HalfmoveClock,
+            FenError::InvalidFullmoveNumber => FenField::FullmoveNumber
+        }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "fen is missing the {:?} field", field),
+            FenError::WrongRankCount(ranks) => write!(f, "fen has {} ranks, expected 8", ranks),
+            FenError::BadRankLength(rank) => write!(f, "rank {} does not have exactly 8 squares", rank),
+            FenError::UnknownPiece(piece) => write!(f, "unknown piece '{}'", piece),
+            FenError::MissingKing{white} => write!(f, "{} has no king", if *white {"white"} else {"black"}),
+            FenError::TooManyKings{white} => write!(f, "{} has more than one king", if *white {"white"} else {"black"}),
+            FenError::PawnOnBackRank => write!(f, "pawns can not be on the first or last rank"),
+            FenError::ImpossibleMaterial{white} => write!(f, "{} has more pawns and promoted pieces than it can have", if *white {"white"} else {"black"}),
+            FenError::OpponentInCheck => write!(f, "the side that is not to move is in check"),
+            FenError::InvalidSideToMove => write!(f, "side to move must be 'w' or 'b'"),
+            FenError::InvalidCastling => write!(f, "castling rights must be '-' or a combination of 'KQkq'"),
+            FenError::InvalidEnPassant => write!(f, "en passant square must be '-' or a square name"),
+            FenError::ImpossibleEnPassant => write!(f, "no pawn can have moved past the en passant square"),
+            FenError::InvalidHalfmoveClock => write!(f, "halfmove clock must be a number from 0 to {}", MAX_HALFMOVE_CLOCK),
+            FenError::InvalidFullmoveNumber => write!(f, "fullmove number must be a positive number")
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+//returned by Position::try_make_move when a move can not be played
+#[derive(Copy,Clone, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    InvalidUci, //the move is not written in standard uci format
+    InvalidSan, //the move is not written in standard algebraic notation
+    AmbiguousSan, //the san move matches more than one legal move
+    IllegalMove,
+    GameOver
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::InvalidUci => write!(f, "move is not in uci format, example e2e4 or a7a8q"),
+            MoveError::InvalidSan => write!(f, "move is not in san format, example Nf3 or exd5"),
+            MoveError::AmbiguousSan => write!(f, "move matches more than one legal move"),
+            MoveError::IllegalMove => write!(f, "move is not legal in this position"),
+            MoveError::GameOver => write!(f, "game is already over")
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+const STARTPOS_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+const PIECES: [Role; 6] = [Pawn, Knight, Bishop, Rook, Queen, King];
+
+//the game is drawn automatically after 75 moves by both sides without a capture or pawn move, so the clock never gets higher
+const MAX_HALFMOVE_CLOCK: u32 = 150;
+
+const PROMOTIONS: [Role; 4] = [Queen, Rook, Knight, Bishop];
+
+//squares that have to be empty between the king and rook for castling
+//white kingside, white queenside, black kingside, black queenside
+const CASTLING_EMPTY_SQUARES: [u64; 4] = [0b110u64, 0b1110000u64, 0b110u64 << 56, 0b1110000u64 << 56];
+
+//squares the king starts on, passes through and ends on, none of them may be attacked
+const CASTLING_KING_SQUARES: [u64; 4] = [0b1110u64, 0b111000u64, 0b1110u64 << 56, 0b111000u64 << 56];
 
-const PROMOTIONS: [Piece; 4] = [Queen, Rook, Knight, Bishop];
+//the king and rook squares, if a piece moves from or to any of them that castling right is lost
+const CASTLING_RIGHTS_SQUARES: [u64; 4] = [0b1001u64, 0b10001000u64, 0b1001u64 << 56, 0b10001000u64 << 56];
+
+//h1 is a light square
+const LIGHT_SQUARES: u64 = 0xAA55AA55AA55AA55u64;
 
 const SQUARE_NAME: [&str; 64] = [//this is also the order of the squares used throughout the engine
         "h1", "g1", "f1", "e1", "d1", "c1", "b1", "a1",
@@ -33,17 +302,78 @@ const SQUARE_NAME: [&str; 64] = [//this is also the order of the squares used th
     ];
 
 
-#[derive(Debug, Clone)]
-struct Move {
+//a legal move, get them from Position.legal_moves() or parse them with Position.uci_to_move() and Position.san_to_move()
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
     from: u64,
     destination: u64,
-    piece: Piece,
-    promotion: Piece
+    piece: Role,
+    promotion: Option<Role>
+}
+
+impl Move {
+    pub fn from_square(self) -> Square {
+        Square::from_bitboard(self.from)
+    }
+
+    pub fn to_square(self) -> Square {
+        Square::from_bitboard(self.destination)
+    }
+
+    //the piece that moves, a pawn for promotions
+    pub fn role(self) -> Role {
+        self.piece
+    }
+
+    pub fn promotion(self) -> Option<Role> {
+        self.promotion
+    }
+
+    //castling is written as a king move of two squares
+    pub fn is_castling(self) -> bool {
+        is_castling(&self)
+    }
+
+    pub fn to_uci(self) -> String {
+        let mut uci = SQUARE_NAME[self.from.trailing_zeros() as usize].to_string() + SQUARE_NAME[self.destination.trailing_zeros() as usize];
+        if let Some(promotion) = self.promotion {
+            uci.push(promotion.char());
+        }
+        uci
+    }
+}
+
+//moves are written in uci format
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_uci())
+    }
+}
+
+//what Position.play() changed that can not be found from the move, give it back to Position.unmake_move() to take the move back
+//a History keeps them so the whole game can be taken back
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Undo {
+    m: Move,
+    captured: Option<Role>,
+    en_passent_target_square: u64,
+    castling_rights: [bool; 4],
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    hash: u64, //the hash before the move, also used for repetitions
+}
+
+impl Undo {
+    //the move that was played
+    pub fn played_move(self) -> Move {
+        self.m
+    }
 }
 
 
 //use startpos() or from_fen() to create a new position
-#[derive(Clone)]
+//a position is small and Copy, it does not know the moves that led to it, keep the Undo from play() or use a History to take back moves and find repetitions
+#[derive(Copy, Clone, Debug)]
 pub struct Position {
     w_board: [u64; 6],
     b_board: [u64; 6],
@@ -51,115 +381,387 @@ pub struct Position {
     b_all: u64,
     w_turn: bool, //true if white; false if black
     en_passent_target_square: u64,
-    //castling_rights: [bool; 4], //white kingside, white queenside, black kingside, blackqueenside
-    legal_moves: Vec<Move>,
+    castling_rights: [bool; 4], //white kingside, white queenside, black kingside, black queenside
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    hash: u64, //zobrist key, used to find repetitions
 }
 
 impl Position {
 
     //returns the default chess position
     pub fn startpos() -> Position {
-        Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+        Position::from_fen(STARTPOS_FEN).unwrap()
     }
 
     fn empty() -> Position {
-        Position { w_board: [0; 6], w_all: 0, b_board: [0; 6], b_all: 0, w_turn: true, en_passent_target_square: 0, legal_moves: vec![]}
+        Position { w_board: [0; 6], w_all: 0, b_board: [0; 6], b_all: 0, w_turn: true, en_passent_target_square: 0, castling_rights: [false; 4],
+            halfmove_clock: 0, fullmove_number: 1, hash: 0}
     }
 
-    //parses a fen string to a chess position
-    pub fn from_fen(fen_string: &str) -> Position {
-        let mut ptr = 0b1u64 << 63; //start at a8
+    //parses a fen string to a chess position, returns an error telling which field is invalid
+    //if the string is not a valid fen, the halfmove clock and fullmove number may be left out
+    pub fn from_fen(fen_string: &str) -> Result<Position, FenError> {
         let mut position = Position::empty();
+        let mut fields = fen_string.split_whitespace();
 
-        let separator = fen_string.chars().position(|c| c == ' ').unwrap();
-        let (fen_board, flags) = (&fen_string[..separator], &fen_string[separator..]);
-
-        for byte in fen_board.as_bytes().iter() {
-            if byte <= &b'9' && byte >= &b'1' {
-                ptr >>= byte-b'0';
-                continue;
-            }
-            match byte {
-                b'r' => {position.b_board[Rook as usize] |= ptr},
-                b'b' => {position.b_board[Bishop as usize] |= ptr},
-                b'n' => {position.b_board[Knight as usize] |= ptr},
-                b'q' => {position.b_board[Queen as usize] |= ptr},
-                b'k' => {position.b_board[King as usize] |= ptr},
-                b'p' => {position.b_board[Pawn as usize] |= ptr},
-                b'R' => {position.w_board[Rook as usize] |= ptr},
-                b'B' => {position.w_board[Bishop as usize] |= ptr},
-                b'N' => {position.w_board[Knight as usize] |= ptr},
-                b'Q' => {position.w_board[Queen as usize] |= ptr},
-                b'K' => {position.w_board[King as usize] |= ptr},
-                b'P' => {position.w_board[Pawn as usize] |= ptr},
-                _ => {} 
-            }
-            if byte != &b'/' {ptr >>= 1;}
+        let fen_board = fields.next().ok_or(FenError::MissingField(FenField::PiecePlacement))?;
+        let ranks: Vec<&str> = fen_board.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::WrongRankCount(ranks.len()))
         }
-        for byte in flags.as_bytes().iter() {
-            if byte == &b'w' {
-                position.w_turn = true;
+        let mut ptr = 0b1u64 << 63; //start at a8
+        for (i, rank) in ranks.iter().enumerate() {
+            let rank_number = 8 - i as u8;
+            let mut files = 0;
+            for byte in rank.bytes() {
+                if (b'1'..=b'8').contains(&byte) {
+                    files += byte - b'0';
+                    ptr >>= byte - b'0';
+                    continue;
+                }
+                let piece = match byte.to_ascii_lowercase() {
+                    b'r' => Rook,
+                    b'b' => Bishop,
+                    b'n' => Knight,
+                    b'q' => Queen,
+                    b'k' => King,
+                    b'p' => Pawn,
+                    _ => return Err(FenError::UnknownPiece(byte as char))
+                };
+                if files >= 8 {
+                    return Err(FenError::BadRankLength(rank_number))
+                }
+                if byte.is_ascii_uppercase() {
+                    position.w_board[piece as usize] |= ptr;
+                }
+                else {
+                    position.b_board[piece as usize] |= ptr;
+                }
+                files += 1;
+                ptr >>= 1;
             }
-            else if byte == &b'b' {
-                position.w_turn = false;
+            if files != 8 {
+                return Err(FenError::BadRankLength(rank_number))
             }
         }
         for piece in PIECES {
             position.w_all |= position.w_board[piece as usize];
             position.b_all |= position.b_board[piece as usize];
         }
-        position.calculate_legal_moves();
-        position
+        for (white, king) in [(true, position.w_board[King as usize]), (false, position.b_board[King as usize])] {
+            match king.count_ones() {
+                0 => return Err(FenError::MissingKing{white}),
+                1 => {},
+                _ => return Err(FenError::TooManyKings{white})
+            }
+        }
+        if (position.w_board[Pawn as usize] | position.b_board[Pawn as usize]) & (RANK[0] | RANK[7]) != 0 {
+            return Err(FenError::PawnOnBackRank)
+        }
+        //every piece beyond the starting ones has to be a promoted pawn
+        //this also keeps the amount of legal moves within what a MoveList can hold
+        for (white, board) in [(true, position.w_board), (false, position.b_board)] {
+            let count = |piece: Role| board[piece as usize].count_ones();
+            let promoted = count(Queen).saturating_sub(1) + count(Rook).saturating_sub(2) + count(Bishop).saturating_sub(2) + count(Knight).saturating_sub(2);
+            if count(Pawn) + promoted > 8 {
+                return Err(FenError::ImpossibleMaterial{white})
+            }
+        }
+
+        position.w_turn = match fields.next() {
+            Some("w") => true,
+            Some("b") => false,
+            Some(_) => return Err(FenError::InvalidSideToMove),
+            None => return Err(FenError::MissingField(FenField::SideToMove))
+        };
+        //the king of the side that just moved can not be in check, the side to move could capture it
+        let (opponent_king, by_white) = if position.w_turn {(position.b_board[King as usize], true)} else {(position.w_board[King as usize], false)};
+        if attackers(&position, by_white, position.w_all | position.b_all, opponent_king.trailing_zeros() as usize) != 0 {
+            return Err(FenError::OpponentInCheck)
+        }
+
+        let castling = fields.next().ok_or(FenError::MissingField(FenField::Castling))?;
+        if castling != "-" {
+            for byte in castling.bytes() {
+                let right = match byte {
+                    b'K' => 0,
+                    b'Q' => 1,
+                    b'k' => 2,
+                    b'q' => 3,
+                    _ => return Err(FenError::InvalidCastling)
+                };
+                if position.castling_rights[right] {
+                    return Err(FenError::InvalidCastling)
+                }
+                position.castling_rights[right] = true;
+            }
+        }
+        //rights without a king and rook on their starting squares can never be used, so they are dropped
+        for (right, squares) in CASTLING_RIGHTS_SQUARES.iter().enumerate() {
+            let king_and_rook = if right < 2 {
+                position.w_board[King as usize] | position.w_board[Rook as usize]
+            }
+            else {
+                position.b_board[King as usize] | position.b_board[Rook as usize]
+            };
+            if king_and_rook & squares != *squares {
+                position.castling_rights[right] = false;
+            }
+        }
+
+        let en_passent = fields.next().ok_or(FenError::MissingField(FenField::EnPassant))?;
+        if en_passent != "-" {
+            let target = square_from_name(en_passent).ok_or(FenError::InvalidEnPassant)?;
+            //the target has to be the empty square behind a pawn that just moved two squares
+            let (target_rank, pawn, start) = if position.w_turn {
+                (RANK[5], position.b_board[Pawn as usize] & (target >> 8), target << 8)
+            }
+            else {
+                (RANK[2], position.w_board[Pawn as usize] & (target << 8), target >> 8)
+            };
+            if target & target_rank == 0 || pawn == 0 || (position.w_all | position.b_all) & (target | start) != 0 {
+                return Err(FenError::ImpossibleEnPassant)
+            }
+            position.en_passent_target_square = target;
+        }
+
+        if let Some(halfmove_clock) = fields.next() {
+            position.halfmove_clock = halfmove_clock.parse().map_err(|_| FenError::InvalidHalfmoveClock)?;
+            if position.halfmove_clock > MAX_HALFMOVE_CLOCK {
+                return Err(FenError::InvalidHalfmoveClock)
+            }
+        }
+        if let Some(fullmove_number) = fields.next() {
+            position.fullmove_number = fullmove_number.parse().map_err(|_| FenError::InvalidFullmoveNumber)?;
+            if position.fullmove_number == 0 {
+                return Err(FenError::InvalidFullmoveNumber)
+            }
+        }
+
+        position.hash = position.calculate_hash();
+        Ok(position)
+    }
+
+    //returns the position as a fen string
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        let mut empty_squares = 0;
+        for square in (0..64).rev() {
+            let bitboard_square = 0b1u64 << square;
+            let (piece, white) = match self.get_w_piece(bitboard_square) {
+                None => (self.get_b_piece(bitboard_square), false),
+                piece => (piece, true)
+            };
+            match piece {
+                None => empty_squares += 1,
+                Some(piece) => {
+                    if empty_squares != 0 {
+                        fen.push_str(&empty_squares.to_string());
+                        empty_squares = 0;
+                    }
+                    let letter = piece.char();
+                    fen.push(if white {letter.to_ascii_uppercase()} else {letter});
+                }
+            }
+            if square % 8 == 0 {
+                if empty_squares != 0 {
+                    fen.push_str(&empty_squares.to_string());
+                    empty_squares = 0;
+                }
+                if square != 0 {
+                    fen.push('/');
+                }
+            }
+        }
+
+        fen.push_str(if self.w_turn {" w "} else {" b "});
+
+        let mut castling = String::new();
+        for (right, letter) in ['K', 'Q', 'k', 'q'].iter().enumerate() {
+            if self.castling_rights[right] {
+                castling.push(*letter);
+            }
+        }
+        fen.push_str(if castling.is_empty() {"-"} else {&castling});
+
+        fen.push(' ');
+        if self.en_passent_target_square == 0 {
+            fen.push('-');
+        }
+        else {
+            fen.push_str(SQUARE_NAME[self.en_passent_target_square.trailing_zeros() as usize]);
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_number));
+        fen
+    }
+
+    pub fn white_to_move(&self) -> bool {
+        self.w_turn
+    }
+
+    pub fn turn(&self) -> Color {
+        if self.w_turn {Color::White} else {Color::Black}
+    }
+
+    //the piece on a square, None if the square is empty
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        let bitboard_square = square.bitboard();
+        if let Some(role) = self.get_w_piece(bitboard_square) {
+            return Some(Piece {color: Color::White, role})
+        }
+        self.get_b_piece(bitboard_square).map(|role| Piece {color: Color::Black, role})
     }
 
-    //checks whether or not there are any legal moves, if there
-    //are no legal moves the game is over, use get_result() to get the result
+    //every square with a piece on it
+    pub fn occupied(&self) -> Bitboard {
+        Bitboard(self.w_all | self.b_all)
+    }
+
+    //the squares of every piece of a color
+    pub fn by_color(&self, color: Color) -> Bitboard {
+        Bitboard(match color {Color::White => self.w_all, Color::Black => self.b_all})
+    }
+
+    //the squares of every piece of one kind, example all white knights
+    pub fn by_piece(&self, piece: Piece) -> Bitboard {
+        let board = match piece.color {Color::White => &self.w_board, Color::Black => &self.b_board};
+        Bitboard(board[piece.role as usize])
+    }
+
+    //number of halfmoves since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    //starts at 1 and is incremented after every black move
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    //checks whether or not the game is over, it is over when there are no legal moves,
+    //when 75 moves have been played by both sides without a capture or pawn move or when neither side can checkmate
+    //a position does not know the moves before it, History.game_in_progress() also ends the game on fivefold repetition
+    //use get_result() to get the result
     pub fn game_in_progress(&self) -> bool {
-        return self.legal_moves.len() != 0
+        !self.generate_moves().is_empty() && self.halfmove_clock < MAX_HALFMOVE_CLOCK && !self.insufficient_material()
+    }
+
+    //true if neither side can checkmate with any sequence of legal moves, the game is then a draw
+    //for example king against king, king and minor piece against king or only bishops on squares of the same color
+    pub fn insufficient_material(&self) -> bool {
+        !self.can_mate(Color::White) && !self.can_mate(Color::Black)
+    }
+
+    //checks whether or not a side has enough material to ever checkmate, even with the help of the opponent
+    //used when a player runs out of time, the game is a draw instead of a loss if the opponent can not mate
+    //the check is conservative, it only returns false when a mate is impossible
+    pub fn can_mate(&self, color: Color) -> bool {
+        let (own, opponent) = match color {
+            Color::White => (&self.w_board, &self.b_board),
+            Color::Black => (&self.b_board, &self.w_board)
+        };
+        if own[Pawn as usize] | own[Rook as usize] | own[Queen as usize] != 0 {
+            return true
+        }
+        let knights = own[Knight as usize].count_ones();
+        let bishops = own[Bishop as usize];
+        let opponent_pieces = opponent[Pawn as usize] | opponent[Knight as usize] | opponent[Bishop as usize] | opponent[Rook as usize] | opponent[Queen as usize];
+        match (knights, bishops != 0) {
+            (0, false) => false,
+            //a single knight can only mate if the opponent has a piece that takes a flight square from its own king
+            (1, false) => opponent_pieces != 0,
+            //bishops can only mate if there is a piece that can block a square of the other color
+            (0, true) => {
+                let all_bishops = bishops | opponent[Bishop as usize];
+                let same_color = all_bishops & LIGHT_SQUARES == 0 || all_bishops & !LIGHT_SQUARES == 0;
+                !same_color || opponent_pieces & !opponent[Bishop as usize] != 0
+            },
+            _ => true
+        }
+    }
+
+    //a player may claim a draw when 50 moves have been played by both sides without a capture or pawn move
+    //or when the same position has occurred three times, repetitions are only found by History.can_claim_draw()
+    //the game continues until the draw is claimed, a checkmate on the last move takes precedence
+    pub fn can_claim_draw(&self) -> bool {
+        self.claim_draw().is_some()
     }
 
     //returns the result of the game, should only be used
-    //after game_in_progress returns false
+    //after game_in_progress returns false or after a draw was claimed with can_claim_draw
     pub fn get_result(&self) -> GameResult {
+        if self.generate_moves().is_empty() && self.in_check() {
+            return if self.w_turn {BlackWin} else {WhiteWin}
+        }
+        Draw
+    }
+
+    //returns the result and the reason if the game is over by the rules, None while it is in progress
+    //draws that have to be claimed are returned by claim_draw instead
+    pub fn outcome(&self) -> Option<Outcome> {
+        let termination = if self.generate_moves().is_empty() {
+            if self.in_check() {Termination::Checkmate} else {Termination::Stalemate}
+        }
+        else if self.insufficient_material() {
+            Termination::InsufficientMaterial
+        }
+        else if self.halfmove_clock >= MAX_HALFMOVE_CLOCK {
+            Termination::FiftyMoveRule
+        }
+        else {
+            return None
+        };
+        Some(Outcome {result: self.get_result(), termination})
+    }
+
+    //returns the draw if the side to move can claim one, None otherwise
+    pub fn claim_draw(&self) -> Option<Outcome> {
+        if !self.game_in_progress() {
+            return None
+        }
+        if self.halfmove_clock < 100 {
+            return None
+        }
+        Some(Outcome {result: Draw, termination: Termination::FiftyMoveRule})
+    }
+
+    //the outcome when a side runs out of time, it loses unless the opponent can not checkmate at all
+    pub fn timeout(&self, color: Color) -> Outcome {
+        let result = if !self.can_mate(!color) {Draw} else if color == Color::White {BlackWin} else {WhiteWin};
+        Outcome {result, termination: Termination::Timeout}
+    }
+
+    //checks if the king of the side to move is attacked
+    fn in_check(&self) -> bool {
         let blocker_board = self.w_all | self.b_all;
         if self.w_turn {
             let king_pos = self.w_board[King as usize].trailing_zeros() as usize;
-            if square_attacked_by_black(self.clone(), blocker_board, king_pos) {
-                return BlackWin
-            }
+            square_attacked_by_black(self, blocker_board, king_pos)
         }
         else {
             let king_pos = self.b_board[King as usize].trailing_zeros() as usize;
-            if square_attacked_by_white(self.clone(), blocker_board, king_pos) {
-                return WhiteWin
-            }
+            square_attacked_by_white(self, blocker_board, king_pos)
         }
-        Draw
     }
 
     //returns all legal moves in standard uci format
-    pub fn get_legal_moves(&mut self) -> Vec<String> {//should only be used for human interaction
+    pub fn get_legal_moves(&self) -> Vec<String> {//should only be used for human interaction
         let mut bitboard_square_to_name: HashMap<u64, &str> = HashMap::new();
-        for square in 0..64 {
+        for (square, square_name) in SQUARE_NAME.iter().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            let square_name = SQUARE_NAME[square];
-            bitboard_square_to_name.insert(bitboard_square, square_name);
+            bitboard_square_to_name.insert(bitboard_square, *square_name);
         }
         
         let mut moves: Vec<String> = vec![];
-        for m in &self.legal_moves {
-            if m.promotion == Void {
-                moves.push(bitboard_square_to_name[&m.from].to_string() + bitboard_square_to_name[&m.destination]);
-            }
-            else {
-                let piece: &str = match m.promotion {
-                    Queen => "q",
-                    Rook => "r",
-                    Knight => "n",
-                    Bishop => "b",
-                    _ => {""}
-                };
-                moves.push(bitboard_square_to_name[&m.from].to_string() + bitboard_square_to_name[&m.destination]+piece);
+        for m in &self.generate_moves() {
+            match m.promotion {
+                None => moves.push(bitboard_square_to_name[&m.from].to_string() + bitboard_square_to_name[&m.destination]),
+                Some(promotion) => {
+                    let piece = promotion.char().to_string();
+                    moves.push(bitboard_square_to_name[&m.from].to_string() + bitboard_square_to_name[&m.destination] + &piece);
+                }
             }
             
         }
@@ -168,7 +770,7 @@ impl Position {
 
 
     //returns all legal moves in standard uci format from a given square
-    pub fn get_square_legal_moves(&mut self, square: &str) -> Vec<String> {//should only be used for human interaction
+    pub fn get_square_legal_moves(&self, square: &str) -> Vec<String> {//should only be used for human interaction
         let mut square_legal_moves: Vec<String> = vec![];
         let legal_moves = self.get_legal_moves();
         for m in legal_moves {
@@ -179,45 +781,173 @@ impl Position {
         square_legal_moves
     }
 
-    fn calculate_legal_moves(&mut self) {
-        self.legal_moves.clear();
-        let blocker_board = self.w_all | self.b_all; 
-        let mut king_pos: usize = 0;
-        for square in 0..64 {
-            let bitboard_square = 0b1u64 << square;
+    //returns all legal moves as typed moves, they can be played with play() without going through strings
+    pub fn legal_moves(&self) -> MoveList {
+        self.generate_moves()
+    }
+
+    //generates only legal moves, so no move has to be tried on the board to see if it leaves the king in check
+    //https://www.chessprogramming.org/Checks_and_Pinned_Pieces_(Bitboards)
+    //checkers are the pieces giving check, with one checker the other pieces must capture it or block the check
+    //and with two checkers only the king can move
+    //pinned pieces may only move along the line between the king and the pinning piece
+    pub(crate) fn generate_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        let blocker_board = self.w_all | self.b_all;
+        let (own_board, own_all) = if self.w_turn {(self.w_board, self.w_all)} else {(self.b_board, self.b_all)};
+        let king = own_board[King as usize];
+        let king_pos = king.trailing_zeros() as usize;
+
+        let checkers = attackers(self, !self.w_turn, blocker_board, king_pos);
+        let check_mask = match checkers.count_ones() {
+            0 => !0u64,
+            1 => BETWEEN[king_pos][checkers.trailing_zeros() as usize] | checkers,
+            _ => 0u64
+        };
+
+        let pinned = pinned_pieces(self, self.w_turn);
+
+        //the king can not move to an attacked square, it is removed from the board when checking
+        //so it can not step back along the line of a slider that gives check
+        let mut king_moves = KING_MASK[king_pos] & !own_all;
+        let mut king_destinations = 0u64;
+        while king_moves != 0 {
+            let square = king_moves.trailing_zeros() as usize;
+            king_moves &= king_moves - 1;
+            if attackers(self, !self.w_turn, blocker_board & !king, square) == 0 {
+                king_destinations |= 0b1u64 << square;
+            }
+        }
+        add_moves(&mut moves, king_destinations, king, King);
+
+        //in double check only the king can move
+        if check_mask == 0 {
+            return moves
+        }
+        for piece in [Queen, Rook, Bishop, Knight, Pawn] {
+            let mut pieces = own_board[piece as usize];
+            while pieces != 0 {
+                let square = pieces.trailing_zeros() as usize;
+                let bitboard_square = pieces & pieces.wrapping_neg();
+                pieces &= pieces - 1;
+                let allowed = if pinned & bitboard_square != 0 {check_mask & LINE[king_pos][square]} else {check_mask};
+                match (piece, self.w_turn) {
+                    (Queen, true) => self.add_w_queen_moves(&mut moves, square, blocker_board, bitboard_square, allowed),
+                    (Rook, true) => self.add_w_rook_moves(&mut moves, square, blocker_board, bitboard_square, allowed),
+                    (Bishop, true) => self.add_w_bishop_moves(&mut moves, square, blocker_board, bitboard_square, allowed),
+                    (Knight, true) => self.add_w_knight_moves(&mut moves, square, bitboard_square, allowed),
+                    (Pawn, true) => self.add_w_pawn_moves(&mut moves, square, blocker_board, bitboard_square, allowed),
+                    (Queen, false) => self.add_b_queen_moves(&mut moves, square, blocker_board, bitboard_square, allowed),
+                    (Rook, false) => self.add_b_rook_moves(&mut moves, square, blocker_board, bitboard_square, allowed),
+                    (Bishop, false) => self.add_b_bishop_moves(&mut moves, square, blocker_board, bitboard_square, allowed),
+                    (Knight, false) => self.add_b_knight_moves(&mut moves, square, bitboard_square, allowed),
+                    (Pawn, false) => self.add_b_pawn_moves(&mut moves, square, blocker_board, bitboard_square, allowed),
+                    _ => {}
+                }
+            }
+        }
+        self.add_en_passant_moves(&mut moves, king_pos, checkers);
+        if checkers == 0 {
             if self.w_turn {
-                match self.get_w_piece(bitboard_square) {
-                    Queen => {self.add_w_queen_moves(square, blocker_board, bitboard_square)},
-                    Rook => {self.add_w_rook_moves(square, blocker_board, bitboard_square)},
-                    Bishop => {self.add_w_bishop_moves(square, blocker_board, bitboard_square)},
-                    Knight => {self.add_w_knight_moves(square, bitboard_square)},
-                    Pawn => {self.add_w_pawn_moves(square, blocker_board ,bitboard_square)},
-                    King => {self.add_w_king_moves(square, bitboard_square); king_pos = square},
-                    Void => {}
-                } 
+                self.add_w_castling_moves(&mut moves, blocker_board);
             }
             else {
-                match self.get_b_piece(bitboard_square) {
-                    Queen => {self.add_b_queen_moves(square, blocker_board, bitboard_square)},
-                    Rook => {self.add_b_rook_moves(square, blocker_board, bitboard_square)},
-                    Bishop => {self.add_b_bishop_moves(square, blocker_board, bitboard_square)},
-                    Knight => {self.add_b_knight_moves(square, bitboard_square)},
-                    Pawn => {self.add_b_pawn_moves(square, blocker_board, bitboard_square)},
-                    King => {self.add_b_king_moves(square, bitboard_square); king_pos = square},
-                    Void => {}
-                } 
+                self.add_b_castling_moves(&mut moves, blocker_board);
+            }
+        }
+        moves
+    }
+
+    //en passant is the only move that removes a piece from a square other than its destination
+    //so it is checked separately, the pawn that moved and the capturing pawn can both leave the line of a slider
+    //example 8/8/8/KPp4r/8/8/8/7k w - c6, bxc6 would leave the white king in check from the rook
+    fn add_en_passant_moves(&self, moves: &mut MoveList, king_pos: usize, checkers: u64) {
+        if self.en_passent_target_square == 0 {
+            return
+        }
+        let target = self.en_passent_target_square.trailing_zeros() as usize;
+        let (pawns, captured, opponent_board) = if self.w_turn {
+            (B_PAWN_CAPTURE_MASK[target] & self.w_board[Pawn as usize], self.en_passent_target_square >> 8, self.b_board)
+        }
+        else {
+            (W_PAWN_CAPTURE_MASK[target] & self.b_board[Pawn as usize], self.en_passent_target_square << 8, self.w_board)
+        };
+        //a check by a knight or pawn can only be answered by en passant if the pawn captured is the checker
+        let rook_sliders = opponent_board[Rook as usize] | opponent_board[Queen as usize];
+        let bishop_sliders = opponent_board[Bishop as usize] | opponent_board[Queen as usize];
+        if checkers & !captured & !(rook_sliders | bishop_sliders) != 0 {
+            return
+        }
+        let mut pawns = pawns;
+        while pawns != 0 {
+            let from = pawns & pawns.wrapping_neg();
+            pawns &= pawns - 1;
+            let blocker_board = (self.w_all | self.b_all) ^ from ^ captured ^ self.en_passent_target_square;
+            let legal = rook_attacks(king_pos, blocker_board) & rook_sliders == 0
+                && bishop_attacks(king_pos, blocker_board) & bishop_sliders == 0;
+            if legal {
+                moves.push(Move {from, destination: self.en_passent_target_square, piece: Pawn, promotion: None});
+            }
+        }
+    }
+
+    //the zobrist key of the position, it is the same key as in polyglot opening books
+    //covers the pieces, side to move, castling rights and the en passant file
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    //calculates the zobrist key from scratch, moves update it incrementally in update_hash
+    fn calculate_hash(&self) -> u64 {
+        let mut hash = 0;
+        for piece in PIECES {
+            for (white, board) in [(true, self.w_board[piece as usize]), (false, self.b_board[piece as usize])] {
+                let mut board = board;
+                while board != 0 {
+                    let bitboard_square = board & board.wrapping_neg();
+                    hash ^= zobrist::piece_key(white, piece, bitboard_square);
+                    board ^= bitboard_square;
+                }
             }
-            
         }
-        //this removes all moves that leaves the king in check, code is messy and hard to debug and not very fast
-        //so it should definetly be replaced with a proper pinned pieces bitboard implementation
-        let mut moves = self.legal_moves.clone();
         if self.w_turn {
-            moves.retain(|m | self.w_king_capture_filter(m.clone(), king_pos));
-        } else {
-            moves.retain(|m | self.b_king_capture_filter(m.clone(), king_pos));
+            hash ^= zobrist::turn_key();
         }
-        self.legal_moves = moves;
+        hash ^ zobrist::castling_key(self.castling_rights) ^ self.en_passant_hash()
+    }
+
+    //like polyglot the en passant file is only hashed when a pawn of the side to move stands next to the pawn that moved
+    //so positions where en passant is impossible are not told apart
+    fn en_passant_hash(&self) -> u64 {
+        if self.en_passent_target_square == 0 {
+            return 0
+        }
+        let square = self.en_passent_target_square.trailing_zeros() as usize;
+        let can_capture = if self.w_turn {
+            B_PAWN_CAPTURE_MASK[square] & self.w_board[Pawn as usize] != 0
+        }
+        else {
+            W_PAWN_CAPTURE_MASK[square] & self.b_board[Pawn as usize] != 0
+        };
+        if can_capture {zobrist::en_passant_key(self.en_passent_target_square)} else {0}
+    }
+
+    //updates the hash after m was made, undo holds the position before the move
+    fn update_hash(&mut self, m: &Move, undo: &Undo, previous_en_passant_hash: u64) {
+        let white = !self.w_turn;
+        let mut hash = undo.hash ^ zobrist::turn_key() ^ previous_en_passant_hash ^ self.en_passant_hash();
+        hash ^= zobrist::castling_key(undo.castling_rights) ^ zobrist::castling_key(self.castling_rights);
+        hash ^= zobrist::piece_key(white, m.piece, m.from);
+        hash ^= zobrist::piece_key(white, m.promotion.unwrap_or(m.piece), m.destination);
+        if let Some(captured) = undo.captured {
+            hash ^= zobrist::piece_key(!white, captured, captured_square(m, undo, white));
+        }
+        if is_castling(m) {
+            let rook_move = castling_rook_move(m);
+            hash ^= zobrist::piece_key(white, Rook, rook_move & rook_move.wrapping_neg());
+            hash ^= zobrist::piece_key(white, Rook, rook_move & (rook_move - 1));
+        }
+        self.hash = hash;
     }
 
     //plays a move from standard uci format, does not check if the move is legal
@@ -225,39 +955,141 @@ impl Position {
     //uci example "e2e4"  move the piece from e2 to e4
     //promotions in uci are handled by adding a letter after the move q => Queen, r => Rook, n => Knight, b => Bishop
     //example a7a8q    move the peice from a7 to a8 and promote to a Queen
-    //this function can handle castling even tought castling is not yet implemented for get_legal_moves
-    //so to castle simply make the move in standard uci format, nothing will break
+    //to castle move the king two squares in standard uci format, example e1g1
     pub fn make_move(&mut self, m: &str) { //should only be used for human interaction
+        self.play(self.unchecked_uci_to_move(m));
+    }
+
+    //the move make_move plays, panics if there is no piece of the side to move on the from square
+    pub(crate) fn unchecked_uci_to_move(&self, m: &str) -> Move {
         let mut name_to_bitboard_square: HashMap<&str, u64> = HashMap::new();
-        for square in 0..64 {
+        for (square, square_name) in SQUARE_NAME.iter().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            let square_name = SQUARE_NAME[square];
-            name_to_bitboard_square.insert(square_name, bitboard_square);
+            name_to_bitboard_square.insert(*square_name, bitboard_square);
         }
         let from = name_to_bitboard_square[&m[..2]];
         let destination = name_to_bitboard_square[&m[2..4]];
         let promotion_piece = &m[4..];
         let promotion = match  promotion_piece {
-            "q" => Queen,
-            "r" => Rook,
-            "b" => Bishop,
-            "n" => Knight,
-            _ => Void
+            "q" => Some(Queen),
+            "r" => Some(Rook),
+            "b" => Some(Bishop),
+            "n" => Some(Knight),
+            _ => None
             
         };
 
         let piece = if self.w_turn {self.get_w_piece(from)} else {self.get_b_piece(from)};
+        let piece = piece.expect("there is no piece of the side to move on the from square");
+        Move{from, destination, piece, promotion}
+    }
+
+    //plays a move from standard uci format after checking that it is legal, unlike make_move
+    //this never panics so it is safe to use with moves from untrusted input
+    pub fn try_make_move(&mut self, m: &str) -> Result<(), MoveError> {
+        let legal_move = self.checked_uci_to_move(m)?;
+        self.play(legal_move);
+        Ok(())
+    }
+
+    //the move try_make_move plays, with the same errors
+    pub(crate) fn checked_uci_to_move(&self, m: &str) -> Result<Move, MoveError> {
+        parse_uci(m).ok_or(MoveError::InvalidUci)?;
+        if !self.game_in_progress() {
+            return Err(MoveError::GameOver)
+        }
+        let legal_move = self.uci_to_move(m)?;
+        //only possible after make_move played an illegal move, without a king the position can not be used
+        if self.captured_piece(&legal_move) == Some(King) {
+            return Err(MoveError::IllegalMove)
+        }
+        Ok(legal_move)
+    }
+
+    //finds the legal move written in uci format
+    pub fn uci_to_move(&self, m: &str) -> Result<Move, MoveError> {
+        let (from, destination, promotion) = parse_uci(m).ok_or(MoveError::InvalidUci)?;
+        self.generate_moves().iter()
+            .find(|legal| legal.from == from && legal.destination == destination && legal.promotion == promotion)
+            .copied()
+            .ok_or(MoveError::IllegalMove)
+    }
+
+    //plays a typed move, like make_move it does not check the move
+    //the move should be one of the legal moves of this position, from legal_moves(), uci_to_move() or san_to_move()
+    //returns what unmake_move needs to take the move back
+    pub fn play(&mut self, m: Move) -> Undo {
+        let undo = Undo {
+            m,
+            captured: self.captured_piece(&m),
+            en_passent_target_square: self.en_passent_target_square,
+            castling_rights: self.castling_rights,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+        };
+        let en_passant_hash = self.en_passant_hash();
         if self.w_turn {
-            self.make_w_move(Move{from, destination, piece, promotion: promotion});
+            self.make_w_move(m);
         }
         else {
-            self.make_b_move(Move{from, destination, piece, promotion: promotion});
+            self.make_b_move(m);
         }
-        
-        ;
+        self.update_hash(&m, &undo, en_passant_hash);
+        undo
+    }
+
+    //takes back the move undo was returned for, it has to be the last move played on this position
+    //the pieces are moved back and the captured piece, en passant square, castling rights, clocks and hash are restored
+    pub fn unmake_move(&mut self, undo: Undo) {
+        let m = undo.m;
+        let white = !self.w_turn;
+        let (own_board, own_all, other_board, other_all) = if white {
+            (&mut self.w_board, &mut self.w_all, &mut self.b_board, &mut self.b_all)
+        }
+        else {
+            (&mut self.b_board, &mut self.b_all, &mut self.w_board, &mut self.w_all)
+        };
+
+        //the promoted piece turns back into a pawn before it is moved back
+        if let Some(promotion) = m.promotion {
+            own_board[promotion as usize] ^= m.destination;
+            own_board[m.piece as usize] ^= m.destination;
+        }
+        own_board[m.piece as usize] ^= m.from | m.destination;
+        *own_all ^= m.from | m.destination;
+
+        if is_castling(&m) {
+            let rook_move = castling_rook_move(&m);
+            own_board[Rook as usize] ^= rook_move;
+            *own_all ^= rook_move;
+        }
+
+        if let Some(captured) = undo.captured {
+            let square = captured_square(&m, &undo, white);
+            other_board[captured as usize] |= square;
+            *other_all |= square;
+        }
+
+        self.w_turn = white;
+        self.en_passent_target_square = undo.en_passent_target_square;
+        self.castling_rights = undo.castling_rights;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.hash = undo.hash;
     }
 
     fn make_w_move(&mut self, m: Move) {
+
+        //the halfmove clock is reset by pawn moves and captures
+        if m.piece == Pawn || self.b_all & m.destination != 0 {
+            self.halfmove_clock = 0;
+        }
+        else {
+            //the game is already over at MAX_HALFMOVE_CLOCK, moves played after it with make_move keep the clock there so to_fen stays valid
+            self.halfmove_clock = (self.halfmove_clock + 1).min(MAX_HALFMOVE_CLOCK);
+        }
+
         //moving the piece
         self.w_board[m.piece as usize] ^= m.from | m.destination;
         self.w_all ^= m.from | m.destination;
@@ -270,18 +1102,13 @@ impl Position {
             }
         }
 
-        if m.promotion != Void {
+        if let Some(promotion) = m.promotion {
             self.w_board[m.piece as usize] &= !m.destination;
-            self.w_board[m.promotion as usize] |= m.destination
+            self.w_board[promotion as usize] |= m.destination
         }
 
-        else if m.piece == King && (m.from >> 2) == m.destination {
-            self.w_board[Rook as usize] ^= (m.destination >> 1) | (m.destination << 1);
-            self.w_all ^= (m.destination >> 1) | (m.destination << 1);
-        }
-        else if m.piece == King && (m.from << 2) == m.destination {
-            self.w_board[Rook as usize] ^= (m.destination >> 1) | (m.destination << 2);
-            self.w_all ^= (m.destination >> 1) | (m.destination << 2);
+        else if is_castling(&m) {
+            self.move_castling_rook(&m);
         }
 
         else if m.piece == Pawn && m.destination == self.en_passent_target_square {
@@ -289,17 +1116,26 @@ impl Position {
             self.b_board[Pawn as usize] &= !(m.destination >>8);
             
         }
+        self.update_castling_rights(&m);
         self.en_passent_target_square = 0;
         if m.piece == Pawn && (m.from << 16) == m.destination {
             self.en_passent_target_square = m.from << 8;
         }
         
         self.w_turn = false;
-        self.legal_moves.clear();
-        self.calculate_legal_moves();
     }
 
     fn make_b_move(&mut self, m: Move) {
+
+        //the halfmove clock is reset by pawn moves and captures
+        if m.piece == Pawn || self.w_all & m.destination != 0 {
+            self.halfmove_clock = 0;
+        }
+        else {
+            //the game is already over at MAX_HALFMOVE_CLOCK, moves played after it with make_move keep the clock there so to_fen stays valid
+            self.halfmove_clock = (self.halfmove_clock + 1).min(MAX_HALFMOVE_CLOCK);
+        }
+
         //moving the piece
         self.b_board[m.piece as usize] ^= m.from | m.destination;
         self.b_all ^= m.from | m.destination;
@@ -312,233 +1148,197 @@ impl Position {
             }
         }
 
-        if m.promotion != Void {
+        if let Some(promotion) = m.promotion {
             self.b_board[m.piece as usize] &= !m.destination;
-            self.b_board[m.promotion as usize] |= m.destination
+            self.b_board[promotion as usize] |= m.destination
         }
 
-        else if m.piece == King && (m.from >> 2) == m.destination {
-            self.w_board[Rook as usize] ^= (m.destination >> 1) | (m.destination << 1);
-            self.w_all ^= (m.destination >> 1) | (m.destination << 1);
-        }
-        else if m.piece == King && (m.from << 2) == m.destination {
-            self.w_board[Rook as usize] ^= (m.destination >> 1) | (m.destination << 2);
-            self.w_all ^= (m.destination >> 1) | (m.destination << 2);
+        else if is_castling(&m) {
+            self.move_castling_rook(&m);
         }
 
         else if m.piece == Pawn && m.destination == self.en_passent_target_square {
             self.w_all &= !(m.destination << 8);
             self.w_board[Pawn as usize] &= !(m.destination <<8);
         }
+        self.update_castling_rights(&m);
         self.en_passent_target_square = 0;
         if m.piece == Pawn && (m.from >> 16) == m.destination {
             self.en_passent_target_square = m.from >> 8;
         }
         self.w_turn = true;
-        self.legal_moves.clear();
-        self.calculate_legal_moves();
+        self.fullmove_number = self.fullmove_number.saturating_add(1);
     }
 
-    fn w_king_capture_filter(&mut self, m: Move, king_pos: usize) -> bool {
-        let mut king_pos_copy = king_pos;
-        let mut pos_clone = self.clone();
-        
-        //moving the piece
-        pos_clone.w_board[m.piece as usize] ^= m.from | m.destination;
-        pos_clone.w_all ^= m.from | m.destination;
-
-        //clearing captured pieces
-        if pos_clone.b_all & m.destination != 0 {
-            pos_clone.b_all &= !m.destination;
-            for piece in PIECES {
-                pos_clone.b_board[piece as usize] &= !m.destination;
-            }
-        }
-
-        if m.piece == Pawn && m.destination == pos_clone.en_passent_target_square {
-            pos_clone.b_all &= !(m.destination >> 8);
-            pos_clone.b_board[Pawn as usize] &= !(m.destination >>8);
+    //moves the rook of the side to move when castling
+    fn move_castling_rook(&mut self, m: &Move) {
+        let rook_move = castling_rook_move(m);
+        if self.w_turn {
+            self.w_board[Rook as usize] ^= rook_move;
+            self.w_all ^= rook_move;
+            self.castling_rights[0] = false;
+            self.castling_rights[1] = false;
         }
-
-        //if the king is moved we need to update its position
-        if m.piece == King {
-            king_pos_copy = m.destination.trailing_zeros() as usize;
+        else {
+            self.b_board[Rook as usize] ^= rook_move;
+            self.b_all ^= rook_move;
+            self.castling_rights[2] = false;
+            self.castling_rights[3] = false;
         }
-  
-        let blocker_board = pos_clone.w_all | pos_clone.b_all;
-        return !square_attacked_by_black(pos_clone ,blocker_board, king_pos_copy);
     }
 
-    fn b_king_capture_filter(&mut self, m: Move, king_pos: usize) -> bool {
-        let mut king_pos_copy = king_pos;
-        let mut pos_clone = self.clone();
-        
-        //moving the piece
-        pos_clone.b_board[m.piece as usize] ^= m.from | m.destination;
-        pos_clone.b_all ^= m.from | m.destination;
-
-        //clearing captured pieces
-        if pos_clone.w_all & m.destination != 0 {
-            pos_clone.w_all &= !m.destination;
-            for piece in PIECES {
-                pos_clone.w_board[piece as usize] &= !m.destination;
-            }
+    //the piece captured by a move, None if it is not a capture
+    fn captured_piece(&self, m: &Move) -> Option<Role> {
+        if m.piece == Pawn && m.destination == self.en_passent_target_square {
+            Some(Pawn)
         }
-
-        if m.piece == Pawn && m.destination == pos_clone.en_passent_target_square {
-            pos_clone.w_all &= !(m.destination << 8);
-            pos_clone.w_board[Pawn as usize] &= !(m.destination <<8);
+        else if self.w_turn {
+            self.get_b_piece(m.destination)
         }
-
-
-        //if the king is moved we need to update its position
-        if m.piece == King {
-            king_pos_copy = m.destination.trailing_zeros() as usize;
+        else {
+            self.get_w_piece(m.destination)
         }
-
-        let blocker_board = pos_clone.w_all | pos_clone.b_all;
-        return !square_attacked_by_white(pos_clone ,blocker_board, king_pos_copy);
     }
 
-    
-
-    
-
-    fn get_w_piece(&self, bitboard_square: u64) -> Piece {
-        for piece in PIECES {
-            if self.w_board[piece as usize] & bitboard_square == bitboard_square {
-                return piece
+    //castling rights are lost when the king or the rook moves or when the rook is captured
+    fn update_castling_rights(&mut self, m: &Move) {
+        for (right, squares) in CASTLING_RIGHTS_SQUARES.iter().enumerate() {
+            if (m.from | m.destination) & squares != 0 {
+                self.castling_rights[right] = false;
             }
         }
-        Void
     }
 
-    fn get_b_piece(&self, bitboard_square: u64) -> Piece {
-        for piece in PIECES {
-            if self.b_board[piece as usize] & bitboard_square == bitboard_square {
-                return piece
-            }
-        }
-        Void
+    fn get_w_piece(&self, bitboard_square: u64) -> Option<Role> {
+        PIECES.into_iter().find(|piece| self.w_board[*piece as usize] & bitboard_square == bitboard_square)
+    }
+
+    fn get_b_piece(&self, bitboard_square: u64) -> Option<Role> {
+        PIECES.into_iter().find(|piece| self.b_board[*piece as usize] & bitboard_square == bitboard_square)
     }
     
 
-    fn add_w_pawn_moves(&mut self, square: usize, blocker_board: u64  ,bitboard_square: u64){  
-        let mut legal_moves = W_PAWN_FORWARD_MASK[square] & !(blocker_board) |
+    //allowed is the squares the piece may move to without leaving the king in check
+    fn add_w_pawn_moves(&self, moves: &mut MoveList, square: usize, blocker_board: u64, bitboard_square: u64, allowed: u64){
+        let legal_moves = (W_PAWN_FORWARD_MASK[square] & !(blocker_board) |
         W_PAWN_DOUBLEFORWARD_MASK[square] & !(blocker_board | blocker_board << 8) |
-        (W_PAWN_CAPTURE_MASK[square] & (self.b_all | self.en_passent_target_square));
-        self.add_moves(&mut legal_moves, bitboard_square, Pawn);
+        (W_PAWN_CAPTURE_MASK[square] & self.b_all)) & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Pawn);
     }
 
-    fn add_w_king_moves(&mut self, square: usize, bitboard_square: u64){
-        let mut legal_moves = KING_MASK[square] & !self.w_all;
-        self.add_moves(&mut legal_moves, bitboard_square, King);
+    fn add_w_knight_moves(&self, moves: &mut MoveList, square: usize, bitboard_square: u64, allowed: u64){
+        let legal_moves = KNIGHT_MASK[square] & !self.w_all & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Knight);
     }
 
-    fn add_w_knight_moves(&mut self, square: usize, bitboard_square: u64){
-        let mut legal_moves = KNIGHT_MASK[square] & !self.w_all;
-        self.add_moves(&mut legal_moves, bitboard_square, Knight);
+    fn add_w_bishop_moves(&self, moves: &mut MoveList, square: usize, blocker_board: u64, bitboard_square: u64, allowed: u64){
+        let legal_moves = bishop_attacks(square, blocker_board) & !self.w_all & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Bishop);
     }
 
-    fn add_w_bishop_moves(&mut self, square: usize, blocker_board: u64, bitboard_square: u64){
-        let bishop_blocker_board = blocker_board & BISHOP_BLOCKER_MASK[square];
-        let (magic_number, magic_lookup) = &BISHOP_MAGIC_MASK[square];
-        let mut legal_moves = magic_lookup[(bishop_blocker_board.wrapping_mul(*magic_number) >> BISHOP_MAGIC_SHIFT[square]) as usize]
-         & !self.w_all;
-        self.add_moves(&mut legal_moves, bitboard_square, Bishop);
+    fn add_w_rook_moves(&self, moves: &mut MoveList, square: usize, blocker_board: u64, bitboard_square: u64, allowed: u64){
+        let legal_moves = rook_attacks(square, blocker_board) & !self.w_all & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Rook);
     }
 
-    fn add_w_rook_moves(&mut self, square: usize, blocker_board: u64, bitboard_square: u64){
-        let rook_blocker_board = blocker_board & ROOK_BLOCKER_MASK[square];
-        let (magic_number, magic_lookup) = unsafe{&ROOK_MAGIC_MASK[square]};
-        let mut legal_moves = magic_lookup[(rook_blocker_board.wrapping_mul(*magic_number) >> ROOK_MAGIC_SHIFT[square]) as usize]
-         & !self.w_all;
-        self.add_moves(&mut legal_moves, bitboard_square, Rook);
+    fn add_w_queen_moves(&self, moves: &mut MoveList, square: usize, blocker_board: u64, bitboard_square: u64, allowed: u64){
+        let legal_moves = (rook_attacks(square, blocker_board) | bishop_attacks(square, blocker_board)) & !self.w_all & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Queen);
     }
 
-    fn add_w_queen_moves(&mut self, square: usize, blocker_board: u64, bitboard_square: u64){
-        let rook_blocker_board = blocker_board & ROOK_BLOCKER_MASK[square];
-        let (magic_number, magic_lookup) = unsafe{&ROOK_MAGIC_MASK[square]};
-        let legal_rook_moves = magic_lookup[(rook_blocker_board.wrapping_mul(*magic_number) >> ROOK_MAGIC_SHIFT[square]) as usize]
-         & !self.w_all;
-        let bishop_blocker_board = blocker_board & BISHOP_BLOCKER_MASK[square];
-        let (magic_number, magic_lookup) = &BISHOP_MAGIC_MASK[square];
-        let legal_bishop_moves = magic_lookup[(bishop_blocker_board.wrapping_mul(*magic_number) >> BISHOP_MAGIC_SHIFT[square]) as usize]
-          & !self.w_all;
-
-        let mut legal_moves = legal_rook_moves | legal_bishop_moves;
+    //the king may not castle out of, through or into check
+    fn add_w_castling_moves(&self, moves: &mut MoveList, blocker_board: u64) {
+        let king = 0b1u64 << 3; //e1
+        if self.w_board[King as usize] & king == 0 {
+            return
+        }
+        //kingside
+        if self.castling_rights[0] && self.w_board[Rook as usize] & (king >> 3) != 0
+            && blocker_board & CASTLING_EMPTY_SQUARES[0] == 0
+            && !self.castling_squares_attacked_by_black(blocker_board, CASTLING_KING_SQUARES[0]) {
+            moves.push(Move {from: king, destination: king >> 2, piece: King, promotion: None});
+        }
+        //queenside
+        if self.castling_rights[1] && self.w_board[Rook as usize] & (king << 4) != 0
+            && blocker_board & CASTLING_EMPTY_SQUARES[1] == 0
+            && !self.castling_squares_attacked_by_black(blocker_board, CASTLING_KING_SQUARES[1]) {
+            moves.push(Move {from: king, destination: king << 2, piece: King, promotion: None});
+        }
+    }
 
-        self.add_moves(&mut legal_moves, bitboard_square, Queen);
+    fn castling_squares_attacked_by_black(&self, blocker_board: u64, mut squares: u64) -> bool {
+        while squares != 0 {
+            let square = squares.trailing_zeros() as usize;
+            squares &= squares - 1;
+            if square_attacked_by_black(self, blocker_board, square) {
+                return true
+            }
+        }
+        false
     }
 
-    fn add_b_pawn_moves(&mut self, square: usize, blocker_board: u64  ,bitboard_square: u64){  
-        let mut legal_moves = B_PAWN_FORWARD_MASK[square] & !(blocker_board) |
+    fn add_b_pawn_moves(&self, moves: &mut MoveList, square: usize, blocker_board: u64, bitboard_square: u64, allowed: u64){
+        let legal_moves = (B_PAWN_FORWARD_MASK[square] & !(blocker_board) |
         B_PAWN_DOUBLEFORWARD_MASK[square] & !(blocker_board | blocker_board >> 8) |
-        (B_PAWN_CAPTURE_MASK[square] & (self.w_all | self.en_passent_target_square));
-        self.add_moves(&mut legal_moves, bitboard_square, Pawn);
+        (B_PAWN_CAPTURE_MASK[square] & self.w_all)) & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Pawn);
     }
 
-    fn add_b_king_moves(&mut self, square: usize, bitboard_square: u64){
-        let mut legal_moves = KING_MASK[square] & !self.b_all;
-        self.add_moves(&mut legal_moves, bitboard_square, King);
+    fn add_b_knight_moves(&self, moves: &mut MoveList, square: usize, bitboard_square: u64, allowed: u64){
+        let legal_moves = KNIGHT_MASK[square] & !self.b_all & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Knight);
     }
 
-    fn add_b_knight_moves(&mut self, square: usize, bitboard_square: u64){
-        let mut legal_moves = KNIGHT_MASK[square] & !self.b_all;
-        self.add_moves(&mut legal_moves, bitboard_square, Knight);
+    fn add_b_bishop_moves(&self, moves: &mut MoveList, square: usize, blocker_board: u64, bitboard_square: u64, allowed: u64){
+        let legal_moves = bishop_attacks(square, blocker_board) & !self.b_all & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Bishop);
     }
 
-    fn add_b_bishop_moves(&mut self, square: usize, blocker_board: u64, bitboard_square: u64){
-        let bishop_blocker_board = blocker_board & BISHOP_BLOCKER_MASK[square];
-        let (magic_number, magic_lookup) = &BISHOP_MAGIC_MASK[square];
-        let mut legal_moves = magic_lookup[(bishop_blocker_board.wrapping_mul(*magic_number) >> BISHOP_MAGIC_SHIFT[square]) as usize]
-         & !self.b_all;
-        self.add_moves(&mut legal_moves, bitboard_square, Bishop);
+    fn add_b_rook_moves(&self, moves: &mut MoveList, square: usize, blocker_board: u64, bitboard_square: u64, allowed: u64){
+        let legal_moves = rook_attacks(square, blocker_board) & !self.b_all & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Rook);
     }
 
-    fn add_b_rook_moves(&mut self, square: usize, blocker_board: u64, bitboard_square: u64){
-        let rook_blocker_board = blocker_board & ROOK_BLOCKER_MASK[square];
-        let (magic_number, magic_lookup) = unsafe{&ROOK_MAGIC_MASK[square]};
-        let mut legal_moves = magic_lookup[(rook_blocker_board.wrapping_mul(*magic_number) >> ROOK_MAGIC_SHIFT[square]) as usize]
-         & !self.b_all;
-        self.add_moves(&mut legal_moves, bitboard_square, Rook);
+    fn add_b_queen_moves(&self, moves: &mut MoveList, square: usize, blocker_board: u64, bitboard_square: u64, allowed: u64){
+        let legal_moves = (rook_attacks(square, blocker_board) | bishop_attacks(square, blocker_board)) & !self.b_all & allowed;
+        add_moves(moves, legal_moves, bitboard_square, Queen);
     }
 
-    fn add_b_queen_moves(&mut self, square: usize, blocker_board: u64, bitboard_square: u64){
-        let rook_blocker_board = blocker_board & ROOK_BLOCKER_MASK[square];
-        let (magic_number, magic_lookup) = unsafe{&ROOK_MAGIC_MASK[square]};
-        let legal_rook_moves = magic_lookup[(rook_blocker_board.wrapping_mul(*magic_number) >> ROOK_MAGIC_SHIFT[square]) as usize]
-         & !self.b_all;
-        let bishop_blocker_board = blocker_board & BISHOP_BLOCKER_MASK[square];
-        let (magic_number, magic_lookup) = &BISHOP_MAGIC_MASK[square];
-        let legal_bishop_moves = magic_lookup[(bishop_blocker_board.wrapping_mul(*magic_number) >> BISHOP_MAGIC_SHIFT[square]) as usize]
-          & !self.b_all;
-
-        let mut legal_moves = legal_rook_moves | legal_bishop_moves;
-
-        self.add_moves(&mut legal_moves, bitboard_square, Queen);
+    //the king may not castle out of, through or into check
+    fn add_b_castling_moves(&self, moves: &mut MoveList, blocker_board: u64) {
+        let king = 0b1u64 << 59; //e8
+        if self.b_board[King as usize] & king == 0 {
+            return
+        }
+        //kingside
+        if self.castling_rights[2] && self.b_board[Rook as usize] & (king >> 3) != 0
+            && blocker_board & CASTLING_EMPTY_SQUARES[2] == 0
+            && !self.castling_squares_attacked_by_white(blocker_board, CASTLING_KING_SQUARES[2]) {
+            moves.push(Move {from: king, destination: king >> 2, piece: King, promotion: None});
+        }
+        //queenside
+        if self.castling_rights[3] && self.b_board[Rook as usize] & (king << 4) != 0
+            && blocker_board & CASTLING_EMPTY_SQUARES[3] == 0
+            && !self.castling_squares_attacked_by_white(blocker_board, CASTLING_KING_SQUARES[3]) {
+            moves.push(Move {from: king, destination: king << 2, piece: King, promotion: None});
+        }
     }
 
-    fn add_moves(&mut self, bitboard: &mut u64, from: u64, piece: Piece) {
-            while *bitboard != 0 {
-                let last_bit: u64 = *bitboard & !(*bitboard -1); //getting the last bit
-                *bitboard &= *bitboard -1; //removing the last bit
-                if piece == Pawn && last_bit & (RANK[0] | RANK[7]) != 0 { //checking if move is a promotion
-                    for promotion in PROMOTIONS {
-                        self.legal_moves.push(Move {from: from, destination: last_bit, piece: piece, promotion: promotion}); 
-                    }
-                }
-                else {
-                    self.legal_moves.push(Move {from: from, destination: last_bit, piece: piece, promotion: Void}); 
-                }
-                
+    fn castling_squares_attacked_by_white(&self, blocker_board: u64, mut squares: u64) -> bool {
+        while squares != 0 {
+            let square = squares.trailing_zeros() as usize;
+            squares &= squares - 1;
+            if square_attacked_by_white(self, blocker_board, square) {
+                return true
             }
+        }
+        false
     }
 
     //prints the board using chess unicode chars
     pub fn print(&self) {
         let mut rank = 8;
-        for mut square in 0..64 as u8 {
+        for mut square in 0..64u8 {
             if square % 8 == 0 {
                 print!("\n{} ", rank);
                 rank -= 1;
@@ -546,22 +1346,22 @@ impl Position {
             square = 63 - square;
             let bitboard_square = 0b1u64 << square;
             match self.get_w_piece(bitboard_square) {
-                Queen => {print!("♕"); continue;},
-                Rook => {print!("♖"); continue;},
-                Bishop => {print!("♗"); continue;},
-                Knight => {print!("♘"); continue;},
-                Pawn => {print!("♙"); continue;},
-                King => {print!("♔"); continue;},
-                Void => {}
+                Some(Queen) => {print!("♕"); continue;},
+                Some(Rook) => {print!("♖"); continue;},
+                Some(Bishop) => {print!("♗"); continue;},
+                Some(Knight) => {print!("♘"); continue;},
+                Some(Pawn) => {print!("♙"); continue;},
+                Some(King) => {print!("♔"); continue;},
+                None => {}
             };
             match self.get_b_piece(bitboard_square) {
-                Queen => {print!("♛")},
-                Rook => {print!("♜")},
-                Bishop => {print!("♝")},
-                Knight => {print!("♞")},
-                Pawn => {print!("♟︎")},
-                King => {print!("♚")},
-                Void => {print!("_")}
+                Some(Queen) => {print!("♛")},
+                Some(Rook) => {print!("♜")},
+                Some(Bishop) => {print!("♝")},
+                Some(Knight) => {print!("♞")},
+                Some(Pawn) => {print!("♟︎")},
+                Some(King) => {print!("♚")},
+                None => {print!("_")}
             };
         }
         println!("\n  ABCDEFGH");
@@ -570,56 +1370,126 @@ impl Position {
 }
 
 
-fn square_attacked_by_black(position: Position, blocker_board: u64, square: usize) -> bool {
-    if W_PAWN_CAPTURE_MASK[square] & position.b_board[Pawn as usize] != 0 {
-        return true
+//adds a move from the square to every square in the bitboard, pawn moves to the last rank are added once for every promotion
+fn add_moves(moves: &mut MoveList, bitboard: u64, from: u64, piece: Role) {
+    for destination in Bitboard(bitboard) {
+        let destination = destination.bitboard();
+        if piece == Pawn && destination & (RANK[0] | RANK[7]) != 0 { //checking if move is a promotion
+            for promotion in PROMOTIONS {
+                moves.push(Move {from, destination, piece, promotion: Some(promotion)});
+            }
+        }
+        else {
+            moves.push(Move {from, destination, piece, promotion: None});
+        }
     }
-    if KING_MASK[square] & position.b_board[King as usize] != 0 {
-        return true
+}
+
+fn square_from_name(name: &str) -> Option<u64> {
+    SQUARE_NAME.iter().position(|square_name| *square_name == name).map(|square| 0b1u64 << square)
+}
+
+//parses a move in uci format to its from square, destination square and promotion piece
+fn parse_uci(m: &str) -> Option<(u64, u64, Option<Role>)> {
+    if !m.is_ascii() || (m.len() != 4 && m.len() != 5) {
+        return None
+    }
+    let from = square_from_name(&m[..2])?;
+    let destination = square_from_name(&m[2..4])?;
+    let promotion = match &m[4..] {
+        "" => None,
+        "q" => Some(Queen),
+        "r" => Some(Rook),
+        "b" => Some(Bishop),
+        "n" => Some(Knight),
+        _ => return None
+    };
+    Some((from, destination, promotion))
+}
+
+//the squares the rook moves between when castling, found relative to the king destination
+//so the same squares work for both colors
+fn castling_rook_move(m: &Move) -> u64 {
+    if (m.from >> 2) == m.destination {
+        (m.destination >> 1) | (m.destination << 1) //kingside, h-file to f-file
     }
-    if KNIGHT_MASK[square] & position.b_board[Knight as usize] != 0 {
-        return true
+    else {
+        (m.destination >> 1) | (m.destination << 2) //queenside, a-file to d-file
     }
-    let bishop_blocker_board = blocker_board & BISHOP_BLOCKER_MASK[square];
-    let (magic_number, magic_lookup) = BISHOP_MAGIC_MASK[square];
-    let magic_index = bishop_blocker_board.wrapping_mul(magic_number) >> BISHOP_MAGIC_SHIFT[square];
-    if magic_lookup[magic_index as usize] & (position.b_board[Bishop as usize] | position.b_board[Queen as usize]) != 0 {
-        return true
+}
+
+//the square of the piece captured by m, behind the destination for en passant
+fn captured_square(m: &Move, undo: &Undo, white: bool) -> u64 {
+    if m.piece == Pawn && m.destination == undo.en_passent_target_square {
+        if white {m.destination >> 8} else {m.destination << 8}
     }
-    let rook_blocker_board = blocker_board & ROOK_BLOCKER_MASK[square];
-    let (magic_number, magic_lookup) = unsafe{ROOK_MAGIC_MASK[square]};
-    let magic_index = rook_blocker_board.wrapping_mul(magic_number) >> ROOK_MAGIC_SHIFT[square];
-    if magic_lookup[magic_index as usize] & (position.b_board[Rook as usize] | position.b_board[Queen as usize]) != 0 {
-        return true
+    else {
+        m.destination
     }
-    false
+}
 
+//castling is the only way for the king to move two squares
+fn is_castling(m: &Move) -> bool {
+    m.piece == King && ((m.from >> 2) == m.destination || (m.from << 2) == m.destination)
 }
 
-fn square_attacked_by_white(position: Position, blocker_board: u64, square: usize) -> bool {
-    if B_PAWN_CAPTURE_MASK[square] & position.w_board[Pawn as usize] != 0 {
-        return true
-    }
-    if KING_MASK[square] & position.w_board[King as usize] != 0 {
-        return true
+//all pieces of one color that attack the square
+fn attackers(position: &Position, by_white: bool, blocker_board: u64, square: usize) -> u64 {
+    let (board, pawn_capture_mask) = if by_white {
+        (&position.w_board, &*B_PAWN_CAPTURE_MASK)
     }
-    if KNIGHT_MASK[square] & position.w_board[Knight as usize] != 0 {
-        return true
+    else {
+        (&position.b_board, &*W_PAWN_CAPTURE_MASK)
+    };
+    (pawn_capture_mask[square] & board[Pawn as usize])
+        | (KING_MASK[square] & board[King as usize])
+        | (KNIGHT_MASK[square] & board[Knight as usize])
+        | (bishop_attacks(square, blocker_board) & (board[Bishop as usize] | board[Queen as usize]))
+        | (rook_attacks(square, blocker_board) & (board[Rook as usize] | board[Queen as usize]))
+}
+
+//a piece is pinned when it is the only piece between its king and an opponent slider that would attack the king
+fn pinned_pieces(position: &Position, white: bool) -> u64 {
+    let (own_board, own_all, opponent_board, opponent_all) = if white {
+        (&position.w_board, position.w_all, &position.b_board, position.b_all)
     }
-    let bishop_blocker_board = blocker_board & BISHOP_BLOCKER_MASK[square];
-    let (magic_number, magic_lookup) = BISHOP_MAGIC_MASK[square];
-    let magic_index = bishop_blocker_board.wrapping_mul(magic_number) >> BISHOP_MAGIC_SHIFT[square];
-    if magic_lookup[magic_index as usize] & (position.w_board[Bishop as usize] | position.w_board[Queen as usize]) != 0 {
-        return true
+    else {
+        (&position.b_board, position.b_all, &position.w_board, position.w_all)
+    };
+    let king_pos = own_board[King as usize].trailing_zeros() as usize;
+    let blocker_board = own_all | opponent_all;
+    let mut pinned = 0u64;
+    let mut snipers = (rook_attacks(king_pos, opponent_all) & (opponent_board[Rook as usize] | opponent_board[Queen as usize]))
+        | (bishop_attacks(king_pos, opponent_all) & (opponent_board[Bishop as usize] | opponent_board[Queen as usize]));
+    while snipers != 0 {
+        let sniper = snipers.trailing_zeros() as usize;
+        snipers &= snipers - 1;
+        let between = BETWEEN[king_pos][sniper] & blocker_board;
+        if between.count_ones() == 1 && between & own_all != 0 {
+            pinned |= between;
+        }
     }
-    let rook_blocker_board = blocker_board & ROOK_BLOCKER_MASK[square];
-    let (magic_number, magic_lookup) = unsafe{ROOK_MAGIC_MASK[square]};
-    let magic_index = rook_blocker_board.wrapping_mul(magic_number) >> ROOK_MAGIC_SHIFT[square];
-    if magic_lookup[magic_index as usize] & (position.w_board[Rook as usize] | position.w_board[Queen as usize]) != 0 {
-        return true
+    pinned
+}
+
+//the squares a piece on the square attacks, pawns attack diagonally forward
+fn piece_attacks(piece: Role, white: bool, square: usize, blocker_board: u64) -> u64 {
+    match piece {
+        King => KING_MASK[square],
+        Queen => rook_attacks(square, blocker_board) | bishop_attacks(square, blocker_board),
+        Rook => rook_attacks(square, blocker_board),
+        Bishop => bishop_attacks(square, blocker_board),
+        Knight => KNIGHT_MASK[square],
+        Pawn => if white {W_PAWN_CAPTURE_MASK[square]} else {B_PAWN_CAPTURE_MASK[square]}
     }
-    false
+}
+
+fn square_attacked_by_black(position: &Position, blocker_board: u64, square: usize) -> bool {
+    attackers(position, false, blocker_board, square) != 0
+}
 
+fn square_attacked_by_white(position: &Position, blocker_board: u64, square: usize) -> bool {
+    attackers(position, true, blocker_board, square) != 0
 }
 
 
@@ -637,9 +1507,9 @@ fn w_pawn_forward_mask(bitboard_square: u64) -> u64 {
 lazy_static! {
     static ref W_PAWN_FORWARD_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
+        for (square, square_mask) in mask.iter_mut().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            mask[square] = w_pawn_forward_mask(bitboard_square);
+            *square_mask = w_pawn_forward_mask(bitboard_square);
         }
         mask
     };
@@ -652,9 +1522,9 @@ fn w_pawn_doubleforward_mask(bitboard_square: u64) -> u64 {
 lazy_static! {
     static ref W_PAWN_DOUBLEFORWARD_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
+        for (square, square_mask) in mask.iter_mut().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            mask[square] = w_pawn_doubleforward_mask(bitboard_square);
+            *square_mask = w_pawn_doubleforward_mask(bitboard_square);
         }
         mask
     };
@@ -667,9 +1537,9 @@ fn w_pawn_capture_mask(bitboard_square: u64) -> u64 {
 lazy_static! {
     static ref W_PAWN_CAPTURE_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
+        for (square, square_mask) in mask.iter_mut().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            mask[square] = w_pawn_capture_mask(bitboard_square);
+            *square_mask = w_pawn_capture_mask(bitboard_square);
         }
         mask
     };
@@ -682,9 +1552,9 @@ fn b_pawn_forward_mask(bitboard_square: u64) -> u64 {
 lazy_static! {
     static ref B_PAWN_FORWARD_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
+        for (square, square_mask) in mask.iter_mut().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            mask[square] = b_pawn_forward_mask(bitboard_square);
+            *square_mask = b_pawn_forward_mask(bitboard_square);
         }
         mask
     };
@@ -697,9 +1567,9 @@ fn b_pawn_doubleforward_mask(bitboard_square: u64) -> u64 {
 lazy_static! {
     static ref B_PAWN_DOUBLEFORWARD_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
+        for (square, square_mask) in mask.iter_mut().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            mask[square] = b_pawn_doubleforward_mask(bitboard_square);
+            *square_mask = b_pawn_doubleforward_mask(bitboard_square);
         }
         mask
     };
@@ -712,9 +1582,9 @@ fn b_pawn_capture_mask(bitboard_square: u64) -> u64 {
 lazy_static! {
     static ref B_PAWN_CAPTURE_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
+        for (square, square_mask) in mask.iter_mut().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            mask[square] = b_pawn_capture_mask(bitboard_square);
+            *square_mask = b_pawn_capture_mask(bitboard_square);
         }
         mask
     };
@@ -729,9 +1599,9 @@ fn king_mask(bitboard_square: u64) -> u64 {
 lazy_static! {
     static ref KING_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
+        for (square, square_mask) in mask.iter_mut().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            mask[square] = king_mask(bitboard_square);
+            *square_mask = king_mask(bitboard_square);
         }
         mask
     };
@@ -747,32 +1617,14 @@ fn knight_mask(bitboard_square: u64) -> u64 {
 lazy_static! {
     static ref KNIGHT_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
+        for (square, square_mask) in mask.iter_mut().enumerate() {
             let bitboard_square = 0b1u64 << square;
-            mask[square] = knight_mask(bitboard_square);
+            *square_mask = knight_mask(bitboard_square);
         }
         mask
     };
 }  
 
-//fn print_board(board: u64) {
-//    for mut square in 0..64 as u8 {
-//        if square % 8 == 0 {
-//            print!("\n")
-//        }
-//        square = 63 - square;
-//        
-//        let bitboard = 0b1u64 << square;
-//        if board & bitboard == bitboard {
-//            print!("1")
-//        }
-//        else {
-//            print!(".")
-//        }
-//    }
-//    println!();
-//}
-
 
 const ROOK_MAGIC_SHIFT: [u8; 64]=[
 	52, 53, 53, 53, 53, 53, 53, 52,
@@ -785,29 +1637,6 @@ const ROOK_MAGIC_SHIFT: [u8; 64]=[
 	52, 53, 53, 53, 53, 53, 53, 52
 ];
 
-const FILE: [u64; 8] = [
-    0b1000000010000000100000001000000010000000100000001000000010000000u64,
-    0b0100000001000000010000000100000001000000010000000100000001000000u64,
-    0b0010000000100000001000000010000000100000001000000010000000100000u64,
-    0b0001000000010000000100000001000000010000000100000001000000010000u64,
-    0b0000100000001000000010000000100000001000000010000000100000001000u64,
-    0b0000010000000100000001000000010000000100000001000000010000000100u64,
-    0b0000001000000010000000100000001000000010000000100000001000000010u64,
-    0b0000000100000001000000010000000100000001000000010000000100000001u64,
-];
-
-const RANK: [u64; 8] = [
-    0b11111111u64,
-    0b11111111u64 << 8,
-    0b11111111u64 << 16,
-    0b11111111u64 << 24,
-    0b11111111u64 << 32,
-    0b11111111u64 << 40,
-    0b11111111u64 << 48,
-    0b11111111u64 << 56,
-];
-
-
 fn rook_mask(bitboard_square: u64, blocker_board: u64) -> u64 {
     let mut mask = 0b0u64;
     let mut ptr = bitboard_square;
@@ -852,8 +1681,8 @@ fn rook_all_blockers_mask(square: u8) -> u64{
 lazy_static! {
     static ref ROOK_BLOCKER_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
-            mask[square] = rook_all_blockers_mask(square as u8);
+        for (square, square_mask) in mask.iter_mut().enumerate() {
+            *square_mask = rook_all_blockers_mask(square as u8);
         }
         mask
     };
@@ -911,32 +1740,55 @@ fn bishop_all_blockers_mask(square: u8) -> u64{
 lazy_static! {
     static ref BISHOP_BLOCKER_MASK: [u64; 64] = {
         let mut mask: [u64; 64] = [0; 64];
-        for square in 0..64 {
-            mask[square] = bishop_all_blockers_mask(square as u8);
+        for (square, square_mask) in mask.iter_mut().enumerate() {
+            *square_mask = bishop_all_blockers_mask(square as u8);
         }
         mask
     };
 }  
 
-fn find_magic(piece: Piece, square: u8) -> (u64, Vec<u64>) {
-    let mut count = 0;
-    //looping through random numbers until a magic number is found
-    loop {
-        //the random u64 with ands is chosen since move magic number have a small amount of 1s
-        let maybe_magic = thread_rng().gen::<u64>() & thread_rng().gen::<u64>() & thread_rng().gen::<u64>(); 
-        let result: Option<Vec<u64>> = check_if_magic(piece, square, maybe_magic);
-        match result {
-            Some(lookup) => {
-                //the number is magic!
-                println!("found magic after {} attempts", count);
-                return (maybe_magic, lookup)
-            },
-            None => {count += 1}
-        }
-    }
-}
+//magic numbers for every square, found once by looping through random numbers until check_if_magic succeeded
+//the random u64 was the and of three numbers since magic numbers have a small amount of 1s
+//the search is not kept, the magic_numbers test checks that every constant still works
+const ROOK_MAGIC: [u64; 64] = [
+    0x9080001184204004, 0x00c01008a0004000, 0x0500081100c12000, 0x4700090084203000,
+    0x9200060068210410, 0x0080020014000980, 0x00801a0001005080, 0x0900018021450002,
+    0x3000800220400480, 0x2000400042201000, 0x2201001020010940, 0x8000801000800800,
+    0x09010008020c1100, 0x0602001012000884, 0x0441002402000100, 0x0140800244802100,
+    0x9220608000c00090, 0x0090054000c82000, 0x0001010010406001, 0x4801848018003000,
+    0x0404050010080100, 0x0001010006040008, 0x0900040048900621, 0x000102000a81004c,
+    0x1c0440008004208c, 0x1020100340042040, 0x8d02002200348040, 0x0000080080100080,
+    0x1084240080080280, 0x14890c0080020080, 0x0081284400223001, 0x0100884600108114,
+    0x0002400020800880, 0x0002814000802000, 0x0020806000801004, 0x800a002112004008,
+    0x02c2800400800803, 0x0040800400800200, 0x08408a2804000110, 0x040280c582000104,
+    0x0080044460044004, 0x6200a01000404001, 0x0101002000450010, 0x408a00c008120020,
+    0x0201002800110004, 0x2002008084008002, 0x0813000600110004, 0x0801000040810002,
+    0xa001014024800500, 0x02c0a08240090100, 0x0c0a20001106c100, 0x00a8000880100480,
+    0x200908020c008080, 0x020200504804c200, 0x01010042000c1100, 0x000580010000d080,
+    0x0400209201048042, 0x4a01015080400721, 0x0702091020010045, 0x0888100100082045,
+    0x0013001004080023, 0xc022000425081002, 0xc201000200008401, 0xc0000510e4440082,
+];
+
+const BISHOP_MAGIC: [u64; 64] = [
+    0x0088a00c04420420, 0x0002840902021000, 0x0008008102002000, 0x0114124200105200,
+    0x00051040400910c0, 0x0042051008231410, 0x00184c0444c01000, 0x0000420050080400,
+    0x0000102018430442, 0x1080200444104440, 0x0003280805002000, 0x000108060b480010,
+    0x0608011040800024, 0x00020d1016500040, 0x100904040c040601, 0x0c04410900900400,
+    0x4108000c08081842, 0x4402001010021088, 0x101000080081a228, 0x0008094882044040,
+    0x0084000081a00050, 0x0001002210008401, 0x0001002044022000, 0x0600271100821000,
+    0x4108200040840104, 0x1004a00042080b00, 0x80910100100408a2, 0x220500c00c004200,
+    0x08410040a4054000, 0x0848060110c10088, 0x10a4008704088c00, 0x0423120001098980,
+    0x0041041002202000, 0x0284100c11488104, 0x2012034040240100, 0xa020120080080080,
+    0x8802008400020160, 0x0a05014200010500, 0x0202020050040444, 0x4304108028328410,
+    0x0204090c40101001, 0x0182008420004404, 0x0000082088013000, 0x7000204200806800,
+    0x2033400811400202, 0x0001100102000110, 0x0345412602002400, 0x0031240400810048,
+    0x014300d004200480, 0x0202048601108000, 0x0018305200901080, 0x0880058105882002,
+    0x0008045082020000, 0x1004303010012000, 0x204002821c01000a, 0x0804108400408144,
+    0x0001008210030400, 0x00001a0a12120611, 0x0081008042080c00, 0x1002002044840400,
+    0x2820040010020202, 0x2008024052041101, 0x8000206002888100, 0x0028020400440100,
+];
 
-fn check_if_magic(piece: Piece, square: u8, magic_candidate: u64) -> Option<Vec<u64>> {
+fn check_if_magic(piece: Role, square: u8, magic_candidate: u64) -> Option<Vec<u64>> {
    
     let mut lookup: Vec<u64> = 
     if piece == Rook {vec![0; 1 << (64-ROOK_MAGIC_SHIFT[square as usize])]} 
@@ -986,129 +1838,125 @@ fn check_if_magic(piece: Piece, square: u8, magic_candidate: u64) -> Option<Vec<
     }
     //no bad hash collisions
     //this candidate is magic!
-    dbg!(lookup.len());
     Some(lookup)
 }
 
-//lazy_static! {
-//    //vector containing a magic number and lookup table for each square
-//    static ref ROOK_MAGIC_MASK: Vec<(u64, Vec<u64>)> = {
-//        let mut mask: Vec<(u64, Vec<u64>)> = vec![(0, vec![]); 64];
-//        for square in 0..64 as u8{
-//            println!("finding rook magic for square {}...",square);
-//            mask[square as usize] = find_magic(Rook, square);
-//        }
-//        mask
-//    };
-//}  
-//
-//lazy_static! {
-//    //vector containing a magic number and lookup table for each square
-//    static ref BISHOP_MAGIC_MASK: Vec<(u64, Vec<u64>)> = {
-//        let mut mask: Vec<(u64, Vec<u64>)> = vec![(0, vec![]); 64];
-//        for square in 0..64 as u8{
-//            println!("finding bishop magic for square {}...",square);
-//            mask[square as usize] = find_magic(Bishop, square);
-//        }
-//        mask
-//    };
-//}
-
-//} 
-
-//lazy_static! {
-//    static ref ROOK_MAGIC_MASK: [(u64, [u64; 4096]); 64] = {
-//        let mut mask: [(u64, [u64; 4096]); 64] = [(0, [0; 512]); 64];
-//        for square in 0..64 as u8{
-//            println!("finding rock magic for square {}...",square);
-//            let (magic_number, lookup) = find_magic(Rook, square);
-//            mask[square as usize].0 = magic_number;
-//            for i in 0..lookup.len() {
-//                mask[square as usize].1[i] = lookup[i];
-//            }
-//        }
-//        mask
-//    };
-//} 
-
-//lazy_static could not handle an array of this size, so it is time for a static mut
-//would be nice to find an alternative to unsafe
-
-static mut ROOK_MAGIC_MASK: [(u64, [u64; 4096]); 64] = [(0,[0; 4096]); 64];
-
-//must be run to initialize the rook magic mask
-//for safety, run this function before doing anything else
-pub unsafe fn init_rook_magic_mask() {
-    let mut mask: [(u64, [u64; 4096]); 64] = [(0, [0; 4096]); 64];
-        for square in 0..64 as u8{
-            println!("finding rook magic for square {}...",square);
-            let (magic_number, lookup) = find_magic(Rook, square);
-            mask[square as usize].0 = magic_number;
-            for i in 0..lookup.len() {
-                mask[square as usize].1[i] = lookup[i];
+fn magic_mask(piece: Role, magic_numbers: &[u64; 64]) -> Vec<(u64, Vec<u64>)> {
+    (0..64u8).map(|square| {
+        let magic_number = magic_numbers[square as usize];
+        let lookup = check_if_magic(piece, square, magic_number).expect("magic numbers are checked by the tests");
+        (magic_number, lookup)
+    }).collect()
+}
+
+//the lookup tables are kept on the heap since the rook table is too big for the stack
+lazy_static! {
+    //vector containing a magic number and lookup table for each square
+    static ref ROOK_MAGIC_MASK: Vec<(u64, Vec<u64>)> = magic_mask(Rook, &ROOK_MAGIC);
+}
+
+lazy_static! {
+    //vector containing a magic number and lookup table for each square
+    static ref BISHOP_MAGIC_MASK: Vec<(u64, Vec<u64>)> = magic_mask(Bishop, &BISHOP_MAGIC);
+}
+
+//looks up the squares a rook on the square attacks in the magic tables
+fn rook_attacks(square: usize, blocker_board: u64) -> u64 {
+    let rook_blocker_board = blocker_board & ROOK_BLOCKER_MASK[square];
+    let (magic_number, magic_lookup) = &ROOK_MAGIC_MASK[square];
+    magic_lookup[(rook_blocker_board.wrapping_mul(*magic_number) >> ROOK_MAGIC_SHIFT[square]) as usize]
+}
+
+fn bishop_attacks(square: usize, blocker_board: u64) -> u64 {
+    let bishop_blocker_board = blocker_board & BISHOP_BLOCKER_MASK[square];
+    let (magic_number, magic_lookup) = &BISHOP_MAGIC_MASK[square];
+    magic_lookup[(bishop_blocker_board.wrapping_mul(*magic_number) >> BISHOP_MAGIC_SHIFT[square]) as usize]
+}
+
+lazy_static! {
+    //the squares strictly between two squares on the same rank, file or diagonal, empty if they are not on a line
+    static ref BETWEEN: Vec<[u64; 64]> = (0..64).map(|a| {
+        let mut between = [0u64; 64];
+        for (b, squares) in between.iter_mut().enumerate() {
+            let (bitboard_a, bitboard_b) = (0b1u64 << a, 0b1u64 << b);
+            if rook_mask(bitboard_a, 0) & bitboard_b != 0 {
+                *squares = rook_mask(bitboard_a, bitboard_b) & rook_mask(bitboard_b, bitboard_a);
+            }
+            else if bishop_mask(bitboard_a, 0) & bitboard_b != 0 {
+                *squares = bishop_mask(bitboard_a, bitboard_b) & bishop_mask(bitboard_b, bitboard_a);
             }
         }
-        ROOK_MAGIC_MASK = mask; 
+        between
+    }).collect();
 }
 
-
 lazy_static! {
-    static ref BISHOP_MAGIC_MASK: [(u64, [u64; 512]); 64] = {
-        let mut mask: [(u64, [u64; 512]); 64] = [(0, [0; 512]); 64];
-        for square in 0..64 as u8{
-            println!("finding bishop magic for square {}...",square);
-            let (magic_number, lookup) = find_magic(Bishop, square);
-            mask[square as usize].0 = magic_number;
-            for i in 0..lookup.len() {
-                mask[square as usize].1[i] = lookup[i];
+    //the whole rank, file or diagonal through two squares, empty if they are not on a line
+    static ref LINE: Vec<[u64; 64]> = (0..64).map(|a| {
+        let mut line = [0u64; 64];
+        for (b, squares) in line.iter_mut().enumerate() {
+            let (bitboard_a, bitboard_b) = (0b1u64 << a, 0b1u64 << b);
+            if rook_mask(bitboard_a, 0) & bitboard_b != 0 {
+                *squares = (rook_mask(bitboard_a, 0) & rook_mask(bitboard_b, 0)) | bitboard_a | bitboard_b;
+            }
+            else if bishop_mask(bitboard_a, 0) & bitboard_b != 0 {
+                *squares = (bishop_mask(bitboard_a, 0) & bishop_mask(bitboard_b, 0)) | bitboard_a | bitboard_b;
             }
         }
-        mask
-    };
-} 
+        line
+    }).collect();
+}
+
+//the magic tables are built the first time they are used, calling this only builds them ahead of time
+#[deprecated(note = "the magic tables are initialized automatically, this call is no longer needed")]
+pub fn init_rook_magic_mask() {
+    lazy_static::initialize(&ROOK_MAGIC_MASK);
+    lazy_static::initialize(&BISHOP_MAGIC_MASK);
+}
 
-//returns the amount of nodes given a position and a depth
+//returns the amount of nodes given a position and a depth, depth 0 is only the position itself
 pub fn perft(pos: &Position, depth: u8) -> usize {
+    perft_in_place(&mut pos.clone(), depth)
+}
+
+//every move is played and unmade on the same position, so it is the same as before when this returns
+pub(crate) fn perft_in_place(pos: &mut Position, depth: u8) -> usize {
+    if depth == 0 {
+        return 1
+    }
+    let moves = pos.generate_moves();
     if depth == 1 {
-        return pos.legal_moves.len();
+        return moves.len();
     }
     let mut count = 0;
 
-    for m in pos.clone().legal_moves.into_iter() {
-        let mut pos_clone = pos.clone();
-        if pos.w_turn {
-            pos_clone.make_w_move(m);
-        }
-        else {
-            pos_clone.make_b_move(m); 
-        }
-        count += perft(&pos_clone, depth-1);   
+    for m in &moves {
+        let undo = pos.play(*m);
+        count += perft_in_place(pos, depth-1);
+        pos.unmake_move(undo);
     }
     count
 }
 
-
 // --------------------------
 // ######### TESTS ##########
 // --------------------------
-//------------------------------------------------------------------
-//IMPORTANT to run these tests use RUST_MIN_STACK=8388608 cargo test 
-//------------------------------------------------------------------
-//running with the standard stack size for tests will cause overflow
-//tests are slow due to initialization of magic numbers
+//the magic lookup tables are built from the magic numbers in ROOK_MAGIC and BISHOP_MAGIC
 //perft consists of positions made to catch movgen bugs from https://www.chessprogramming.org/Perft_Results
 //where the nodecount at a certain depth is compared to the expected values
 
 #[cfg(test)]
 mod tests {
     use super::Position;
+    use super::history::History;
     use super::perft;
-    use super::init_rook_magic_mask;
     use super::GameResult::*;
+    use super::{FenError, FenField, MoveError, Outcome, Termination};
+    use super::{Role, check_if_magic, ROOK_MAGIC, BISHOP_MAGIC};
+    use super::{Bitboard, Color, File, Piece, Rank, Square};
     
     #[test]
     fn perft1() {
-        unsafe{init_rook_magic_mask()};
         assert_eq!(perft(&Position::startpos(), 1), 20);
         assert_eq!(perft(&Position::startpos(), 2), 400);
         assert_eq!(perft(&Position::startpos(), 3), 8902);
@@ -1116,50 +1964,431 @@ mod tests {
     }
     #[test]
     fn perft2() {
-        unsafe{init_rook_magic_mask()};
-        assert_eq!(perft(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - "), 1), 14);
-        assert_eq!(perft(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - "), 2), 191);
-        assert_eq!(perft(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - "), 3), 2812);
-        assert_eq!(perft(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - "), 4), 43238);
+        assert_eq!(perft(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ").unwrap(), 1), 14);
+        assert_eq!(perft(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ").unwrap(), 2), 191);
+        assert_eq!(perft(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ").unwrap(), 3), 2812);
+        assert_eq!(perft(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ").unwrap(), 4), 43238);
     }
 
     #[test]
     fn perft3() {
-        unsafe{init_rook_magic_mask()};
-        assert_eq!(perft(&Position::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 "), 1), 46);
-        assert_eq!(perft(&Position::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 "), 2), 2079);
-        assert_eq!(perft(&Position::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 "), 3), 89890);
-        assert_eq!(perft(&Position::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 "), 4), 3894594);
+        assert_eq!(perft(&Position::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ").unwrap(), 1), 46);
+        assert_eq!(perft(&Position::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ").unwrap(), 2), 2079);
+        assert_eq!(perft(&Position::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ").unwrap(), 3), 89890);
+        assert_eq!(perft(&Position::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ").unwrap(), 4), 3894594);
+    }
+
+    //kiwipete and position 4 from https://www.chessprogramming.org/Perft_Results
+    //these cover castling for both colors, including black castling at depth 3
+    #[test]
+    fn perft_kiwipete() {
+        assert_eq!(perft(&Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ").unwrap(), 1), 48);
+        assert_eq!(perft(&Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ").unwrap(), 2), 2039);
+        assert_eq!(perft(&Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ").unwrap(), 3), 97862);
+    }
+
+    #[test]
+    fn perft4() {
+        assert_eq!(perft(&Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap(), 1), 6);
+        assert_eq!(perft(&Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap(), 2), 264);
+        assert_eq!(perft(&Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap(), 3), 9467);
+        assert_eq!(perft(&Position::from_fen("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1").unwrap(), 3), 9467);
+    }
+
+    //positions made to catch bugs with pins, checks, en passant, castling and promotions
+    //from https://www.chessprogramming.org/Perft_Results and http://www.rocechess.ch/perft.html
+    #[test]
+    fn perft_tricky() {
+        let positions = [
+            ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 3, 62379),
+            ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6, 1134888), //en passant would leave the king in check
+            ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 6, 1015133),
+            ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1440467), //en passant gives check
+            ("8/8/8/KPp4r/8/8/8/7k w - c6 0 1", 1, 4), //en passant removes two pieces from the rank the king is on
+            ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6, 661072), //castling gives check
+            ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6, 803711),
+            ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4, 1274206),
+            ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4, 1720476),
+            ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6, 3821001), //promotion gives check
+            ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5, 1004658), //discovered check
+            ("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6, 217342),
+            ("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6, 92683),
+            ("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6, 2217), //stalemate
+            ("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7, 567584),
+            ("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4, 23527), //double check
+        ];
+        for (fen, depth, nodes) in positions {
+            assert_eq!(perft(&Position::from_fen(fen).unwrap(), depth), nodes, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn castling() {
+        assert_eq!(perft(&Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap(), 1), 26);
+        assert_eq!(perft(&Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap(), 2), 568);
+
+        //the king may not pass through the attacked f1 square
+        let pos = Position::from_fen("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1").unwrap();
+        assert!(!pos.get_legal_moves().contains(&"e1g1".to_string()));
+        assert!(pos.get_legal_moves().contains(&"e1c1".to_string()));
+
+        //moving the rook loses the castling right on that side
+        let mut pos = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        pos.make_move("h1h2");
+        pos.make_move("a8a7");
+        assert!(!pos.get_legal_moves().contains(&"e1g1".to_string()));
+        assert!(pos.get_legal_moves().contains(&"e1c1".to_string()));
+    }
+
+    //plays and unmakes every move in depth on the same position and checks that everything is restored
+    fn assert_unmake_restores(pos: &mut Position, depth: u8) {
+        if depth == 0 {
+            return
+        }
+        let before = *pos;
+        for m in pos.generate_moves().iter() {
+            let undo = pos.play(*m);
+            assert_eq!(undo.played_move(), *m);
+            assert_unmake_restores(pos, depth - 1);
+            pos.unmake_move(undo);
+            assert_eq!((pos.w_board, pos.b_board, pos.w_all, pos.b_all), (before.w_board, before.b_board, before.w_all, before.b_all), "{}", m);
+            assert_eq!(pos.to_fen(), before.to_fen());
+            assert_eq!(pos.hash, before.hash);
+        }
+    }
+
+    #[test]
+    fn unmake_move() {
+        assert_unmake_restores(&mut Position::startpos(), 3);
+        assert_unmake_restores(&mut Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ").unwrap(), 3);
+        assert_unmake_restores(&mut Position::from_fen("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1").unwrap(), 3);
+        assert_unmake_restores(&mut Position::from_fen("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1").unwrap(), 3);
+        assert_unmake_restores(&mut Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 149 120").unwrap(), 2);
+    }
+
+    #[test]
+    fn invalid_fen() {
+        assert_eq!(Position::from_fen("").err(), Some(FenError::MissingField(FenField::PiecePlacement)));
+        assert_eq!(Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").err(), Some(FenError::MissingField(FenField::SideToMove)));
+        assert_eq!(Position::from_fen("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").err(), Some(FenError::WrongRankCount(7)));
+        assert_eq!(Position::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").err(), Some(FenError::UnknownPiece('9')));
+        assert_eq!(Position::from_fen("rnbqkbnr/pppppppp/8/8/4P4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").err(), Some(FenError::BadRankLength(4)));
+        assert_eq!(Position::from_fen("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").err(), Some(FenError::UnknownPiece('x')));
+        assert_eq!(Position::from_fen("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1").err(), Some(FenError::MissingKing{white: false}));
+        assert_eq!(Position::from_fen("P3k3/8/8/8/8/8/8/4K3 w - - 0 1").err(), Some(FenError::PawnOnBackRank));
+        //13 white queens would need 12 promotions
+        assert_eq!(Position::from_fen("Q6Q/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/3Q4/k1Q2K1Q w - - 0 1").err(), Some(FenError::ImpossibleMaterial{white: true}));
+        assert_eq!(Position::from_fen("4k3/pppppppp/p7/8/8/8/8/4K3 w - - 0 1").err(), Some(FenError::ImpossibleMaterial{white: false}));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/PPPPPPPP/NNK4N w - - 0 1").err(), Some(FenError::ImpossibleMaterial{white: true}));
+        assert!(Position::from_fen("4k3/8/8/8/8/8/PPPPPPPN/NNK5 w - - 0 1").is_ok());
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/4K3 x - - 0 1").err(), Some(FenError::InvalidSideToMove));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/4K3 w KX - 0 1").err(), Some(FenError::InvalidCastling));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - e9 0 1").err(), Some(FenError::InvalidEnPassant));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - e6 0 1").err(), Some(FenError::ImpossibleEnPassant));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - x 1").err(), Some(FenError::InvalidHalfmoveClock));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 4294967295 1").err(), Some(FenError::InvalidHalfmoveClock));
+        assert!(Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 150 1").is_ok());
+        assert_eq!(Position::from_fen("4k3/4R3/8/8/8/8/8/4K3 w - - 0 1").err(), Some(FenError::OpponentInCheck));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/4r3/4K3 b - - 0 1").err(), Some(FenError::OpponentInCheck));
+        assert!(Position::from_fen("4k3/4R3/8/8/8/8/8/4K3 b - - 0 1").is_ok());
+        assert_eq!(FenError::OpponentInCheck.field(), FenField::SideToMove);
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 0").err(), Some(FenError::InvalidFullmoveNumber));
+        assert_eq!(FenError::ImpossibleEnPassant.field(), FenField::EnPassant);
+
+        //the en passant square is parsed and can be captured on
+        let pos = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert!(pos.get_legal_moves().contains(&"e5d6".to_string()));
+    }
+
+    //checks that every position reached in depth moves survives a trip through to_fen and from_fen
+    fn assert_fen_round_trip(pos: &Position, depth: u8) {
+        assert_eq!(Position::from_fen(&pos.to_fen()).unwrap().to_fen(), pos.to_fen());
+        if depth == 0 {
+            return
+        }
+        for m in pos.get_legal_moves() {
+            let mut pos_clone = *pos;
+            pos_clone.make_move(&m);
+            assert_fen_round_trip(&pos_clone, depth - 1);
+        }
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        let mut pos = Position::startpos();
+        assert_eq!(pos.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        pos.make_move("e2e4");
+        assert_eq!(pos.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        pos.make_move("g8f6");
+        assert_eq!(pos.to_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2");
+
+        assert_fen_round_trip(&Position::startpos(), 3);
+        assert_fen_round_trip(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ").unwrap(), 3);
+        assert_fen_round_trip(&Position::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ").unwrap(), 2);
+        assert_fen_round_trip(&Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ").unwrap(), 2);
+        assert_fen_round_trip(&Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap(), 3);
+
+        //the clock stops at 150 so moves made after the game is over still give a fen that can be read
+        let mut pos = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 150 120").unwrap();
+        pos.make_move("a1a2");
+        assert_eq!(pos.to_fen(), "4k3/8/8/8/8/8/R7/4K3 b - - 150 120");
+        assert_fen_round_trip(&pos, 2);
+    }
+
+    #[test]
+    fn try_make_move() {
+        let mut pos = Position::startpos();
+        assert_eq!(pos.try_make_move(""), Err(MoveError::InvalidUci));
+        assert_eq!(pos.try_make_move("e2"), Err(MoveError::InvalidUci));
+        assert_eq!(pos.try_make_move("e2e9"), Err(MoveError::InvalidUci));
+        assert_eq!(pos.try_make_move("e2e4x"), Err(MoveError::InvalidUci));
+        assert_eq!(pos.try_make_move("é2e4"), Err(MoveError::InvalidUci));
+        assert_eq!(pos.try_make_move("e2e5"), Err(MoveError::IllegalMove));
+        assert_eq!(pos.try_make_move("e7e5"), Err(MoveError::IllegalMove));
+        assert_eq!(pos.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(pos.try_make_move("e2e4"), Ok(()));
+
+        let mut pos = Position::from_fen("8/P3k3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(pos.try_make_move("a7a8"), Err(MoveError::IllegalMove));
+        assert_eq!(pos.try_make_move("a7a8n"), Ok(()));
+
+        let mut pos = Position::from_fen("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(pos.try_make_move("a8b8"), Err(MoveError::GameOver));
+
+        //a king can never be captured, not even after make_move left it in check
+        assert!(Position::from_fen("4k3/4R3/8/8/8/8/8/4K3 w - - 0 1").is_err());
+        let mut pos = Position::from_fen("4k3/8/8/8/8/8/4r3/3K4 w - - 0 1").unwrap();
+        pos.make_move("d1e1");
+        assert_eq!(pos.try_make_move("e2e1"), Err(MoveError::IllegalMove));
+        assert_eq!(pos.try_make_san_move("Rxe1"), Err(MoveError::IllegalMove));
+        assert!(pos.try_make_move("e2e3").is_ok());
+    }
+
+    #[test]
+    fn typed_moves() {
+        let mut pos = Position::startpos();
+        assert_eq!(pos.turn(), Color::White);
+        assert_eq!(pos.piece_at(Square::new(File::E, Rank::First)), Some(Piece {color: Color::White, role: Role::King}));
+        assert_eq!(pos.piece_at(Square::new(File::D, Rank::Eighth)), Piece::from_char('q'));
+        assert_eq!(pos.piece_at(Square::new(File::E, Rank::Fourth)), None);
+        assert_eq!(pos.occupied().count(), 32);
+        assert_eq!(pos.by_color(Color::Black), Bitboard::rank(Rank::Seventh) | Bitboard::rank(Rank::Eighth));
+        assert_eq!(pos.by_piece(Piece {color: Color::White, role: Role::Knight}).map(|square| square.name()).collect::<Vec<_>>(), ["g1", "b1"]);
+
+        let legal_moves = pos.legal_moves();
+        assert_eq!(legal_moves.len(), 20);
+        let uci: Vec<String> = legal_moves.iter().map(|m| m.to_string()).collect();
+        assert_eq!(uci, pos.get_legal_moves());
+
+        let m = pos.uci_to_move("g1f3").unwrap();
+        assert_eq!((m.from_square().name(), m.to_square().name(), m.role(), m.promotion()), ("g1", "f3", Role::Knight, None));
+        assert_eq!(pos.san_to_move("Nf3"), Ok(m));
+        assert_eq!(pos.move_to_san(m).unwrap(), "Nf3");
+        assert_eq!(pos.uci_to_move("g1g3"), Err(MoveError::IllegalMove));
+        pos.play(m);
+        assert_eq!(pos.turn(), Color::Black);
+        assert_eq!(pos.move_to_san(m), Err(MoveError::IllegalMove));
+
+        let pos = Position::from_fen("8/1P6/8/8/8/8/6k1/4K3 w - - 0 1").unwrap();
+        let m = pos.uci_to_move("b7b8n").unwrap();
+        assert_eq!(m.promotion(), Some(Role::Knight));
+        assert_eq!(pos.move_to_san(m).unwrap(), "b8=N");
+        assert!(pos.san_to_move("O-O").is_err());
+    }
+
+    #[test]
+    fn magic_numbers() {
+        for square in 0..64u8 {
+            assert!(check_if_magic(Role::Rook, square, ROOK_MAGIC[square as usize]).is_some(), "bad rook magic on square {}", square);
+            assert!(check_if_magic(Role::Bishop, square, BISHOP_MAGIC[square as usize]).is_some(), "bad bishop magic on square {}", square);
+        }
+        //the tables are built on first use from whichever thread gets there first
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let threads: Vec<_> = (0..4).map(|_| std::thread::spawn(move || perft(&Position::from_fen(fen).unwrap(), 2))).collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 2039);
+        }
     }
 
     // example test
     // check that game state is in progress after initialisation
     #[test]
     fn game_in_progress_after_init() {
-        assert_eq!(Position::startpos().game_in_progress(), true);
+        assert!(Position::startpos().game_in_progress());
     }
 
     #[test]
     fn scolars_mate_from_startpos() {
-        unsafe{init_rook_magic_mask()};
         let mut pos = Position::startpos();
         assert_eq!(pos.get_legal_moves().len(), 20);
-        assert_eq!(pos.game_in_progress(), true);
+        assert!(pos.game_in_progress());
         pos.make_move("e2e4");
-        assert_eq!(pos.game_in_progress(), true);
+        assert!(pos.game_in_progress());
         pos.make_move("e7e5");
-        assert_eq!(pos.game_in_progress(), true);
+        assert!(pos.game_in_progress());
         pos.make_move("d1h4");
-        assert_eq!(pos.game_in_progress(), true);
+        assert!(pos.game_in_progress());
         pos.make_move("b8c6");
-        assert_eq!(pos.game_in_progress(), true);
+        assert!(pos.game_in_progress());
         pos.make_move("f1c4");
-        assert_eq!(pos.game_in_progress(), true);
+        assert!(pos.game_in_progress());
         pos.make_move("g8f6");
-        assert_eq!(pos.game_in_progress(), true);
+        assert!(pos.game_in_progress());
         pos.make_move("h4f7");
-        assert_eq!(pos.game_in_progress(), false);
+        assert!(!pos.game_in_progress());
         assert_eq!(pos.get_result(), WhiteWin)
     }
 
+    #[test]
+    fn fifty_move_rule() {
+        let mut pos = History::new(Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 98 80").unwrap());
+        pos.make_move("a1a2");
+        assert_eq!(pos.halfmove_clock(), 99);
+        assert!(!pos.can_claim_draw());
+        pos.make_move("e8d8");
+        assert_eq!((pos.halfmove_clock(), pos.fullmove_number()), (100, 81));
+        assert!(pos.can_claim_draw());
+        assert!(pos.game_in_progress());
+        assert_eq!(pos.get_result(), Draw);
+        pos.unmake_move();
+        assert!(!pos.can_claim_draw());
+
+        let mut pos = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 149 120").unwrap();
+        assert!(pos.game_in_progress());
+        pos.make_move("a1a2");
+        assert!(!pos.game_in_progress());
+        assert_eq!(pos.get_result(), Draw);
+        assert_eq!(pos.try_make_move("e8d8"), Err(MoveError::GameOver));
+
+        //checkmate on the move that reaches 75 moves still wins
+        let mut pos = Position::from_fen("3k4/8/3K4/8/8/8/8/7R w - - 149 120").unwrap();
+        pos.make_move("h1h8");
+        assert!(!pos.game_in_progress());
+        assert_eq!(pos.get_result(), WhiteWin);
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let dead = [
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1", //king against king
+            "8/8/4k3/8/8/3K4/8/6N1 w - - 0 1", //knight against king
+            "8/8/4k3/8/8/3K4/8/5B2 b - - 0 1", //bishop against king
+            "8/8/4k1b1/8/8/3K4/8/5B2 w - - 0 1", //bishops on squares of the same color
+            "b7/1b6/4k3/8/8/3K4/8/5B1B w - - 0 1",
+        ];
+        for fen in dead {
+            let pos = Position::from_fen(fen).unwrap();
+            assert!(pos.insufficient_material(), "{}", fen);
+            assert!(!pos.game_in_progress(), "{}", fen);
+            assert_eq!(pos.get_result(), Draw);
+        }
+        let alive = [
+            "8/8/4k3/8/8/3K4/8/5BB1 w - - 0 1", //bishops on both colors
+            "8/8/4k3/8/8/3K4/8/5BN1 w - - 0 1",
+            "8/8/4k3/8/8/3K4/8/4NN2 w - - 0 1",
+            "8/8/4k3/8/8/3K4/8/4Bb2 w - - 0 1", //bishops on squares of different colors
+            "8/8/4kn2/8/8/3K4/8/5B2 w - - 0 1", //the knight can block
+            "8/8/4k3/8/8/3K4/7P/8 w - - 0 1",
+        ];
+        for fen in alive {
+            let pos = Position::from_fen(fen).unwrap();
+            assert!(!pos.insufficient_material(), "{}", fen);
+            assert!(pos.game_in_progress(), "{}", fen);
+        }
+
+        let pos = Position::from_fen("8/8/4k3/8/8/3K4/8/6N1 w - - 0 1").unwrap();
+        assert!(!pos.can_mate(Color::White));
+        assert!(!pos.can_mate(Color::Black));
+        //a knight can mate a king that is blocked in by its own pawn
+        let pos = Position::from_fen("7k/7p/8/8/8/3K4/8/6N1 w - - 0 1").unwrap();
+        assert!(pos.can_mate(Color::White));
+        assert!(pos.can_mate(Color::Black));
+        let pos = Position::from_fen("7k/8/8/8/8/3K4/8/R7 w - - 0 1").unwrap();
+        assert!(pos.can_mate(Color::White));
+        assert!(!pos.can_mate(Color::Black));
+
+        //capturing the last pawn ends the game
+        let mut pos = Position::from_fen("8/8/4k3/3p4/8/3K4/8/6N1 b - - 0 1").unwrap();
+        assert!(pos.game_in_progress());
+        pos.make_move("d5d4");
+        pos.make_move("d3d4");
+        assert!(!pos.game_in_progress());
+        assert_eq!(pos.get_result(), Draw);
+    }
+
+    #[test]
+    fn outcome() {
+        let outcome = |fen: &str| Position::from_fen(fen).unwrap().outcome();
+        assert_eq!(Position::startpos().outcome(), None);
+        assert_eq!(outcome("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"), Some(Outcome {result: WhiteWin, termination: Termination::Checkmate}));
+        assert_eq!(outcome("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), Some(Outcome {result: Draw, termination: Termination::Stalemate}));
+        assert_eq!(outcome("8/8/4k3/8/8/3K4/8/6N1 w - - 0 1"), Some(Outcome {result: Draw, termination: Termination::InsufficientMaterial}));
+        assert_eq!(outcome("4k3/8/8/8/8/8/8/R3K3 w - - 150 120"), Some(Outcome {result: Draw, termination: Termination::FiftyMoveRule}));
+        //checkmate comes before the 75 move rule
+        assert_eq!(outcome("R5k1/5ppp/8/8/8/8/8/6K1 b - - 150 120").unwrap().termination, Termination::Checkmate);
+
+        let mut pos = History::new(Position::startpos());
+        assert_eq!(pos.claim_draw(), None);
+        for _ in 0..2 {
+            for m in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                pos.make_move(m);
+            }
+        }
+        assert_eq!(pos.claim_draw(), Some(Outcome {result: Draw, termination: Termination::Repetition}));
+        assert_eq!(pos.outcome(), None);
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap().claim_draw().unwrap().termination, Termination::FiftyMoveRule);
+
+        //running out of time against a lone king is a draw
+        let pos = Position::from_fen("7k/8/8/8/8/3K4/8/R7 w - - 0 1").unwrap();
+        assert_eq!(pos.timeout(Color::White), Outcome {result: Draw, termination: Termination::Timeout});
+        assert_eq!(pos.timeout(Color::Black), Outcome {result: WhiteWin, termination: Termination::Timeout});
+
+        assert_eq!(WhiteWin.to_string(), "1-0");
+        assert_eq!(BlackWin.to_string(), "0-1");
+        assert_eq!(Draw.to_string(), "1/2-1/2");
+        assert_eq!(Outcome {result: BlackWin, termination: Termination::Resignation}.to_string(), "0-1 by resignation");
+        assert_eq!(Termination::Timeout.pgn_tag(), "time forfeit");
+        assert_eq!(Termination::Checkmate.pgn_tag(), "normal");
+    }
+
+    #[test]
+    fn zobrist_hash() {
+        //reference keys from the polyglot book format
+        let keys = [
+            (vec![], 0x463b96181691fc9c),
+            (vec!["e2e4"], 0x823c9b50fd114196),
+            (vec!["e2e4", "d7d5"], 0x0756b94461c50fb0),
+            (vec!["e2e4", "d7d5", "e4e5"], 0x662fafb965db29d4),
+            (vec!["e2e4", "d7d5", "e4e5", "f7f5"], 0x22a48b5a8e47ff78),
+            (vec!["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"], 0x652a607ca3f242c1),
+            (vec!["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"], 0x00fdd303c946bdd9),
+            (vec!["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"], 0x3c8123ea7b067637),
+            (vec!["a2a4", "b7b5", "h2h4", "b5b4", "c2c4", "b4c3", "a1a3"], 0x5c3f9b829b279560),
+        ];
+        for (moves, key) in keys {
+            let mut pos = Position::startpos();
+            for m in &moves {
+                pos.make_move(m);
+            }
+            assert_eq!(pos.hash(), key, "{:?}", moves);
+            assert_eq!(Position::from_fen(&pos.to_fen()).unwrap().hash(), key, "{:?}", moves);
+        }
+
+        //the incremental hash matches the hash calculated from scratch after every kind of move
+        fn walk(pos: &Position, depth: u8) {
+            assert_eq!(pos.hash(), Position::from_fen(&pos.to_fen()).unwrap().hash(), "{}", pos.to_fen());
+            if depth == 0 {
+                return
+            }
+            for m in pos.get_legal_moves() {
+                let mut child = *pos;
+                child.make_move(&m);
+                walk(&child, depth - 1);
+            }
+        }
+        walk(&Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap(), 2);
+        walk(&Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap(), 3);
+        walk(&Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap(), 2);
+    }
+
 }
@@ -0,0 +1,81 @@
+//the entries of the TranspositionTable and the PerftTable, shared between threads without locks
+//every entry is two atomics, the key is stored xored with the data so an entry torn by two threads writing at once is never matched
+//https://www.chessprogramming.org/Shared_Hash_Table#Lockless
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const ENTRY_SIZE: usize = 16; //two u64
+
+pub(crate) struct LocklessTable {
+    entries: Vec<[AtomicU64; 2]>, //the key xor the data and the data
+}
+
+impl LocklessTable {
+    //creates a table using at most size_mb megabytes, the number of entries is rounded down to a power of two
+    pub fn new(size_mb: usize) -> LocklessTable {
+        let entries = size_mb.max(1) * 1024 * 1024 / ENTRY_SIZE;
+        let entries = 1 << entries.ilog2();
+        LocklessTable {entries: (0..entries).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect()}
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn size_mb(&self) -> usize {
+        self.entries.len() * ENTRY_SIZE / (1024 * 1024)
+    }
+
+    //the entry a hash is stored in, the size is a power of two so the low bits of the hash are used
+    pub fn index(&self, hash: u64) -> usize {
+        (hash as usize) & (self.entries.len() - 1)
+    }
+
+    //returns the key and the data in the entry, None if it is empty
+    //a torn entry gives a key that matches nothing, so the key always has to be compared
+    pub fn load(&self, index: usize) -> Option<(u64, u64)> {
+        let entry = &self.entries[index];
+        let data = entry[1].load(Ordering::Relaxed);
+        if data == 0 {
+            return None
+        }
+        Some((entry[0].load(Ordering::Relaxed) ^ data, data))
+    }
+
+    //data 0 is an empty entry, so it can not be stored
+    pub fn store(&self, index: usize, key: u64, data: u64) {
+        let entry = &self.entries[index];
+        entry[0].store(key ^ data, Ordering::Relaxed);
+        entry[1].store(data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for entry in &self.entries {
+            entry[0].store(0, Ordering::Relaxed);
+            entry[1].store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LocklessTable;
+
+    #[test]
+    fn store_and_load() {
+        let table = LocklessTable::new(1);
+        assert_eq!((table.len(), table.size_mb()), (65536, 1));
+        assert_eq!(LocklessTable::new(3).size_mb(), 2);
+        let key = 0x1234_5678_9abc_def0;
+        let index = table.index(key);
+        assert_eq!(table.load(index), None);
+        table.store(index, key, 42);
+        assert_eq!(table.load(index), Some((key, 42)));
+        assert_eq!(table.index(key ^ (1 << 63)), index);
+        //a write that only reached the data is not seen as the old key
+        table.entries[index][1].store(43, std::sync::atomic::Ordering::Relaxed);
+        assert_ne!(table.load(index), Some((key, 43)));
+        table.clear();
+        assert_eq!(table.load(index), None);
+    }
+}
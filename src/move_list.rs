@@ -0,0 +1,84 @@
+//a list of moves stored on the stack so generating moves never allocates
+//218 is the most legal moves any chess position has, from_fen rejects material that could allow more
+
+use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut};
+
+use crate::Move;
+
+pub(crate) const MAX_MOVES: usize = 218;
+
+//the moves after len are never read, so they are left uninitialized instead of writing all 218 moves every time a list is made
+//the list can not grow past MAX_MOVES, the move generator relies on every position having at most 218 legal moves
+//that only holds for positions from from_fen and the legal moves played from them
+//make_move and play do not check their move, an illegal one like a knight promoting to a queen can make a position with more moves
+#[derive(Copy, Clone)]
+pub struct MoveList {
+    moves: [MaybeUninit<Move>; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        MoveList {moves: [MaybeUninit::uninit(); MAX_MOVES], len: 0}
+    }
+
+    //panics if the list already has MAX_MOVES moves
+    pub fn push(&mut self, m: Move) {
+        assert!(self.len < MAX_MOVES, "more than {} moves, the position can not be reached with legal moves", MAX_MOVES);
+        self.moves[self.len].write(m);
+        self.len += 1;
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList::new()
+    }
+}
+
+//the list can be used like a slice of the moves in it
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        //SAFETY: the first len moves have been written by push
+        unsafe {std::slice::from_raw_parts(self.moves.as_ptr() as *const Move, self.len)}
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        //SAFETY: the first len moves have been written by push
+        unsafe {std::slice::from_raw_parts_mut(self.moves.as_mut_ptr() as *mut Move, self.len)}
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MAX_MOVES;
+    use crate::Position;
+
+    #[test]
+    fn max_moves() {
+        //the position with the most legal moves known
+        let pos = Position::from_fen("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1").unwrap();
+        assert_eq!(pos.generate_moves().len(), MAX_MOVES);
+        assert_eq!(Position::startpos().generate_moves().len(), 20);
+    }
+}
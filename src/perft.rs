@@ -0,0 +1,362 @@
+//tools for checking the move generator against known perft numbers
+//https://www.chessprogramming.org/Perft_Results
+
+use std::fmt;
+use std::ops::AddAssign;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::{perft_in_place, FenError, Move, Position};
+use crate::lockless::LocklessTable;
+use crate::Role::*;
+
+//the node count after every legal move, compare it with another engine to find the move where the counts differ
+//at depth 0 no moves are played so the divide is empty
+pub fn perft_divide(pos: &Position, depth: u8) -> Vec<(Move, usize)> {
+    if depth == 0 {
+        return vec![]
+    }
+    let mut pos = *pos;
+    pos.generate_moves().iter().map(|&m| {
+        let undo = pos.play(m);
+        let count = perft_in_place(&mut pos, depth - 1);
+        pos.unmake_move(undo);
+        (m, count)
+    }).collect()
+}
+
+const COUNT_BITS: u32 = 56; //the depth is stored above the count
+const COUNT_MASK: u64 = (1 << COUNT_BITS) - 1;
+
+//a cache of node counts so positions reached by different move orders are only counted once
+//it is shared between threads without locks the same way as the TranspositionTable
+pub struct PerftTable {
+    entries: LocklessTable, //the depth and count, stored by zobrist key
+}
+
+impl PerftTable {
+    //creates a table using at most size_mb megabytes, the number of entries is rounded down to a power of two
+    pub fn new(size_mb: usize) -> PerftTable {
+        PerftTable {entries: LocklessTable::new(size_mb)}
+    }
+
+    //the same position is stored in a different entry for every depth
+    fn index(&self, key: u64, depth: u8) -> usize {
+        self.entries.index(key ^ (depth as u64).wrapping_mul(0x9e3779b97f4a7c15))
+    }
+
+    fn probe(&self, key: u64, depth: u8) -> Option<usize> {
+        match self.entries.load(self.index(key, depth)) {
+            Some((stored_key, data)) if stored_key == key && (data >> COUNT_BITS) as u8 == depth => Some((data & COUNT_MASK) as usize),
+            _ => None
+        }
+    }
+
+    //the newest count always replaces the old one
+    fn store(&self, key: u64, depth: u8, count: usize) {
+        let data = (depth as u64) << COUNT_BITS | (count as u64 & COUNT_MASK);
+        self.entries.store(self.index(key, depth), key, data);
+    }
+}
+
+//like perft but counts that are already in the table are not counted again
+pub fn perft_with_table(pos: &Position, depth: u8, table: &PerftTable) -> usize {
+    perft_with_table_in_place(&mut pos.clone(), depth, table)
+}
+
+fn perft_with_table_in_place(pos: &mut Position, depth: u8, table: &PerftTable) -> usize {
+    if depth == 0 {
+        return 1
+    }
+    let moves = pos.generate_moves();
+    if depth == 1 {
+        return moves.len()
+    }
+    if let Some(count) = table.probe(pos.hash(), depth) {
+        return count
+    }
+    let mut count = 0;
+    for &m in &moves {
+        let undo = pos.play(m);
+        count += perft_with_table_in_place(pos, depth - 1, table);
+        pos.unmake_move(undo);
+    }
+    table.store(pos.hash(), depth, count);
+    count
+}
+
+//perft with the root moves split between threads, every thread takes the next move that nobody has counted yet
+//with 0 threads one thread is used for every cpu, the table is optional and can be shared by all threads
+pub fn perft_parallel(pos: &Position, depth: u8, threads: usize, table: Option<&PerftTable>) -> usize {
+    if depth == 0 {
+        return 1
+    }
+    let moves = pos.generate_moves();
+    if depth == 1 {
+        return moves.len()
+    }
+    let threads = if threads == 0 {thread::available_parallelism().map_or(1, |cpus| cpus.get())} else {threads};
+    let next_move = AtomicUsize::new(0);
+    let total = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..threads.min(moves.len()) {
+            scope.spawn(|| {
+                //every thread plays its moves on its own copy
+                let mut pos = *pos;
+                let mut count = 0;
+                while let Some(&m) = moves.get(next_move.fetch_add(1, Ordering::Relaxed)) {
+                    let undo = pos.play(m);
+                    count += match table {
+                        Some(table) => perft_with_table_in_place(&mut pos, depth - 1, table),
+                        None => perft_in_place(&mut pos, depth - 1)
+                    };
+                    pos.unmake_move(undo);
+                }
+                total.fetch_add(count, Ordering::Relaxed);
+            });
+        }
+    });
+    total.into_inner()
+}
+
+//the leaf nodes of a perft split up like in the chessprogramming tables
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: usize,
+    pub captures: usize, //en passant captures are counted here too
+    pub en_passant: usize,
+    pub castles: usize,
+    pub promotions: usize,
+    pub checks: usize, //checkmates are counted here too
+    pub checkmates: usize,
+}
+
+impl AddAssign for PerftStats {
+    fn add_assign(&mut self, other: PerftStats) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passant += other.en_passant;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.checkmates += other.checkmates;
+    }
+}
+
+//like perft but also counts what kind of moves the last moves were, this is much slower than perft
+//at depth 0 the only node is the position itself and no move was played to reach it
+pub fn perft_stats(pos: &Position, depth: u8) -> PerftStats {
+    perft_stats_in_place(&mut pos.clone(), depth)
+}
+
+fn perft_stats_in_place(pos: &mut Position, depth: u8) -> PerftStats {
+    let mut stats = PerftStats::default();
+    if depth == 0 {
+        stats.nodes = 1;
+        return stats
+    }
+    for &m in &pos.generate_moves() {
+        let undo = pos.play(m);
+        if depth > 1 {
+            stats += perft_stats_in_place(pos, depth - 1);
+            pos.unmake_move(undo);
+            continue;
+        }
+        stats.nodes += 1;
+        if undo.captured.is_some() {
+            stats.captures += 1;
+        }
+        if m.piece == Pawn && m.destination == undo.en_passent_target_square {
+            stats.en_passant += 1;
+        }
+        if m.is_castling() {
+            stats.castles += 1;
+        }
+        if m.promotion.is_some() {
+            stats.promotions += 1;
+        }
+        if pos.in_check() {
+            stats.checks += 1;
+            if pos.generate_moves().is_empty() {
+                stats.checkmates += 1;
+            }
+        }
+        pos.unmake_move(undo);
+    }
+    stats
+}
+
+//a position of a perft suite with its expected node counts
+//a line in the file looks like
+//rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ;D1 20 ;D2 400 ;D3 8902
+#[derive(Clone, Debug)]
+pub struct EpdEntry {
+    pub line: usize, //counted from 1
+    pub position: Position,
+    pub counts: Vec<(u8, usize)>, //depth and node count
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EpdError {
+    InvalidFen{line: usize, error: FenError},
+    InvalidCount{line: usize, field: String}, //a field that is not written like D3 8902
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EpdError::InvalidFen{line, error} => write!(f, "line {}: invalid fen: {}", line, error),
+            EpdError::InvalidCount{line, field} => write!(f, "line {}: invalid node count '{}'", line, field)
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+//reads every position of a perft suite, empty lines and lines starting with # are skipped
+pub fn parse_epd(epd: &str) -> Result<Vec<EpdEntry>, EpdError> {
+    let mut entries = vec![];
+    for (i, text) in epd.lines().enumerate() {
+        let line = i + 1;
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let mut fields = text.split(';');
+        let fen = fields.next().unwrap_or("").trim();
+        let position = Position::from_fen(fen).map_err(|error| EpdError::InvalidFen{line, error})?;
+        let mut counts = vec![];
+        for field in fields.map(str::trim).filter(|field| !field.is_empty()) {
+            let invalid = || EpdError::InvalidCount{line, field: field.to_string()};
+            let (depth, count) = field.strip_prefix('D').and_then(|field| field.split_once(' ')).ok_or_else(invalid)?;
+            let depth = depth.parse::<u8>().ok().filter(|depth| *depth > 0).ok_or_else(invalid)?;
+            let count = count.trim().parse::<usize>().map_err(|_| invalid())?;
+            counts.push((depth, count));
+        }
+        entries.push(EpdEntry {line, position, counts});
+    }
+    Ok(entries)
+}
+
+//a node count that is not the one in the suite
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub line: usize,
+    pub fen: String,
+    pub depth: u8,
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {} depth {} expected {} found {}", self.line, self.fen, self.depth, self.expected, self.found)
+    }
+}
+
+//runs perft on every position of the suite up to max_depth and returns the counts that do not match
+//the counts are made on every cpu without a table so the zobrist keys can not hide a bug in the move generator
+pub fn run_suite(entries: &[EpdEntry], max_depth: u8) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+    for entry in entries {
+        for &(depth, expected) in entry.counts.iter().filter(|(depth, _)| *depth <= max_depth) {
+            let found = perft_parallel(&entry.position, depth, 0, None);
+            if found != expected {
+                mismatches.push(Mismatch {line: entry.line, fen: entry.position.to_fen(), depth, expected, found});
+            }
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_epd, perft_divide, perft_parallel, perft_stats, perft_with_table, run_suite, EpdError, PerftStats, PerftTable};
+    use crate::{perft, Position};
+
+    #[test]
+    fn divide() {
+        let pos = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let divide = perft_divide(&pos, 3);
+        assert_eq!(divide.len(), 48);
+        assert_eq!(divide.iter().map(|(_, count)| count).sum::<usize>(), perft(&pos, 3));
+        let castle = divide.iter().find(|(m, _)| m.to_uci() == "e1g1").unwrap();
+        assert_eq!(castle.1, 2059);
+        assert!(perft_divide(&pos, 1).iter().all(|(_, count)| *count == 1));
+    }
+
+    #[test]
+    fn depth_zero() {
+        //the only node at depth 0 is the position itself, also when it has no legal moves
+        for fen in ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"] {
+            let pos = Position::from_fen(fen).unwrap();
+            assert_eq!(perft(&pos, 0), 1);
+            assert!(perft_divide(&pos, 0).is_empty());
+            assert_eq!(perft_stats(&pos, 0), PerftStats {nodes: 1, ..Default::default()});
+            assert_eq!(perft_with_table(&pos, 0, &PerftTable::new(1)), 1);
+            assert_eq!(perft_parallel(&pos, 0, 4, None), 1);
+        }
+    }
+
+    #[test]
+    fn parallel_and_hashed() {
+        let table = PerftTable::new(1);
+        for (fen, depth, count) in [
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 3, 97862),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5, 674624),
+            ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3, 9467),
+            ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 3, 62379),
+        ] {
+            let pos = Position::from_fen(fen).unwrap();
+            assert_eq!(perft(&pos, depth), count);
+            assert_eq!(perft_with_table(&pos, depth, &table), count);
+            //the second time the counts come from the table
+            assert_eq!(perft_with_table(&pos, depth, &table), count);
+            assert_eq!(perft_parallel(&pos, depth, 4, None), count);
+            assert_eq!(perft_parallel(&pos, depth, 0, Some(&table)), count);
+            assert_eq!(perft_parallel(&pos, 1, 4, None), perft(&pos, 1));
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let kiwipete = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(perft_stats(&kiwipete, 2), PerftStats {nodes: 2039, captures: 351, en_passant: 1, castles: 91, promotions: 0, checks: 3, checkmates: 0});
+        assert_eq!(perft_stats(&Position::startpos(), 4), PerftStats {nodes: 197281, captures: 1576, en_passant: 0, castles: 0, promotions: 0, checks: 469, checkmates: 8});
+        let pos = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(perft_stats(&pos, 4), PerftStats {nodes: 43238, captures: 3348, en_passant: 123, castles: 0, promotions: 0, checks: 1680, checkmates: 17});
+    }
+
+    #[test]
+    fn suite() {
+        let epd = "\
+# a few lines of perftsuite.epd
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ;D1 20 ;D2 400 ;D3 8902
+r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ;D1 48 ;D2 2039 ;D3 97862
+
+4k3/8/8/8/8/8/8/4K2R w K - ;D1 15 ;D2 66 ;D3 1197
+4k3/8/8/8/8/8/8/R3K3 w Q - ;D1 16 ;D2 71 ;D3 1288
+";
+        let entries = parse_epd(epd).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[3].counts, [(1, 16), (2, 71), (3, 1288)]);
+        //the last count is wrong on purpose, it is 1287
+        let mismatches = run_suite(&entries, 3);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!((mismatches[0].line, mismatches[0].depth, mismatches[0].expected, mismatches[0].found), (6, 3, 1288, 1287));
+        assert!(run_suite(&entries, 2).is_empty());
+
+        assert!(matches!(parse_epd("8/8/8 w - - ;D1 1"), Err(EpdError::InvalidFen{line: 1, ..})));
+        assert_eq!(parse_epd("\n4k3/8/8/8/8/8/8/4K2R w K - ;D1 15 ;D2").unwrap_err(), EpdError::InvalidCount{line: 2, field: "D2".to_string()});
+    }
+
+    //the whole suite takes a few minutes, run it with cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn perftsuite() {
+        let entries = parse_epd(include_str!("../perftsuite.epd")).unwrap();
+        assert_eq!(entries.len(), 126);
+        let mismatches = run_suite(&entries, 6);
+        assert!(mismatches.is_empty(), "{}", mismatches.iter().map(|mismatch| mismatch.to_string()).collect::<Vec<_>>().join("\n"));
+    }
+}
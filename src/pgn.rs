@@ -0,0 +1,706 @@
+//reading and writing games in portable game notation
+//https://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm
+
+use std::fmt;
+
+use crate::{FenError, GameResult, MoveError, Outcome, Position, Termination};
+use crate::history::History;
+
+//the seven tag roster, these tags are always written first and in this order
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+//movetext lines are wrapped to stay below 80 characters as the export format recommends
+const MAX_LINE_LENGTH: usize = 79;
+
+//a move in the movetext together with its annotations
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnMove {
+    pub san: String,
+    pub uci: String,
+    pub nags: Vec<u8>, //numeric annotation glyphs, $1 or ! is 1
+    pub starting_comment: Option<String>, //comment written before the first move of a variation
+    pub comment: Option<String>, //comment written after the move
+    pub variations: Vec<Vec<PgnMove>>, //alternatives to this move, each starting from the position before it
+}
+
+//a complete game, the mainline moves are replayed and checked for legality when they are added
+#[derive(Clone, Debug)]
+pub struct Game {
+    tags: Vec<(String, String)>,
+    comment: Option<String>, //comment written before the first move
+    moves: Vec<PgnMove>,
+    result: Option<GameResult>, //None if the game is unfinished, written as *
+    termination: Option<Termination>, //written as the Termination tag when it is known
+    start: Position,
+    position: History, //the moves are kept so repetitions end the game
+}
+
+//returned when a pgn can not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgnError {
+    InvalidTag(String), //the tag pair as it was written
+    InvalidFen(FenError), //the FEN tag is not a valid fen
+    UnclosedComment,
+    UnclosedString, //a tag value with no closing quote
+    UnbalancedVariation,
+    UnexpectedToken(String),
+    IllegalMove{ply: usize, san: String, error: MoveError}, //ply is counted from the start of the (variation) line
+    Empty //there is no game in the input
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag pair [{}]", tag),
+            PgnError::InvalidFen(error) => write!(f, "invalid FEN tag: {}", error),
+            PgnError::UnclosedComment => write!(f, "comment is never closed"),
+            PgnError::UnclosedString => write!(f, "tag value is never closed"),
+            PgnError::UnbalancedVariation => write!(f, "variation parentheses do not match"),
+            PgnError::UnexpectedToken(token) => write!(f, "unexpected '{}' in movetext", token),
+            PgnError::IllegalMove{ply, san, error} => write!(f, "move {} at ply {} could not be played: {}", san, ply, error),
+            PgnError::Empty => write!(f, "no game found")
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+impl Game {
+    //a new game from the standard starting position
+    pub fn new() -> Game {
+        Game::from_position(Position::startpos())
+    }
+
+    //a new game starting from the given fen, the FEN and SetUp tags are set
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        let mut game = Game::from_position(Position::from_fen(fen)?);
+        game.set_tag("SetUp", "1");
+        game.set_tag("FEN", fen);
+        Ok(game)
+    }
+
+    fn from_position(position: Position) -> Game {
+        Game {tags: vec![], comment: None, moves: vec![], result: None, termination: None, start: position, position: History::new(position)}
+    }
+
+    //parses a single game, use parse_pgn to read a file with several games
+    pub fn from_pgn(pgn: &str) -> Result<Game, PgnError> {
+        let mut parser = Parser::new(pgn);
+        parser.parse_game()?.ok_or(PgnError::Empty)
+    }
+
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    //sets a tag, replacing the value if the tag already exists
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string()))
+        }
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn set_comment(&mut self, comment: &str) {
+        self.comment = Some(comment.to_string());
+    }
+
+    //the mainline moves
+    pub fn moves(&self) -> &[PgnMove] {
+        &self.moves
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    //setting the result clears the termination, use set_outcome to set both
+    pub fn set_result(&mut self, result: Option<GameResult>) {
+        self.result = result;
+        self.termination = None;
+    }
+
+    pub fn termination(&self) -> Option<Termination> {
+        self.termination
+    }
+
+    //the result together with why the game ended, None if either is unknown
+    pub fn outcome(&self) -> Option<Outcome> {
+        Some(Outcome {result: self.result?, termination: self.termination?})
+    }
+
+    //None marks the game as unfinished
+    pub fn set_outcome(&mut self, outcome: Option<Outcome>) {
+        self.result = outcome.map(|outcome| outcome.result);
+        self.termination = outcome.map(|outcome| outcome.termination);
+    }
+
+    //the position at the end of the mainline
+    pub fn position(&self) -> &Position {
+        self.position.position()
+    }
+
+    //every position of the mainline, starting with the position before the first move
+    pub fn positions(&self) -> Vec<Position> {
+        let mut position = self.start;
+        let mut positions = vec![position];
+        for m in &self.moves {
+            position.make_move(&m.uci);
+            positions.push(position);
+        }
+        positions
+    }
+
+    //plays a move written in san at the end of the mainline
+    pub fn push_san(&mut self, san: &str) -> Result<(), MoveError> {
+        let uci = self.position.san_to_uci(san)?;
+        self.push(uci)
+    }
+
+    //plays a move written in uci format at the end of the mainline
+    pub fn push_uci(&mut self, uci: &str) -> Result<(), MoveError> {
+        self.push(uci.to_string())
+    }
+
+    fn push(&mut self, uci: String) -> Result<(), MoveError> {
+        if !self.position.game_in_progress() {
+            return Err(MoveError::GameOver)
+        }
+        //uci_to_san also checks that the move is legal
+        let san = self.position.uci_to_san(&uci)?;
+        self.position.make_move(&uci);
+        self.moves.push(PgnMove {san, uci, nags: vec![], starting_comment: None, comment: None, variations: vec![]});
+        //the game ends by itself on checkmate and automatic draws
+        if let Some(outcome) = self.position.outcome() {
+            self.set_outcome(Some(outcome));
+        }
+        Ok(())
+    }
+
+    //adds a comment after the last mainline move
+    pub fn comment_last_move(&mut self, comment: &str) {
+        match self.moves.last_mut() {
+            Some(m) => m.comment = Some(comment.to_string()),
+            None => self.comment = Some(comment.to_string())
+        }
+    }
+
+    //writes the game in the pgn export format
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = if name == "Result" {result_token(self.result)} else {self.tag(name).unwrap_or(default)};
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+        }
+        if let Some(termination) = self.termination {
+            pgn.push_str(&format!("[Termination \"{}\"]\n", termination.pgn_tag()));
+        }
+        for (name, value) in &self.tags {
+            let written = SEVEN_TAG_ROSTER.iter().any(|(roster_name, _)| roster_name == name) || (name == "Termination" && self.termination.is_some());
+            if !written {
+                pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+            }
+        }
+        pgn.push('\n');
+
+        let mut tokens = vec![];
+        if let Some(comment) = &self.comment {
+            tokens.push(comment_token(comment));
+        }
+        write_moves(&mut tokens, &self.moves, self.start.w_turn, self.start.fullmove_number);
+        tokens.push(result_token(self.result).to_string());
+
+        //no space is written inside the parentheses of a variation
+        let mut line_length = 0;
+        let mut previous = String::new();
+        for token in tokens {
+            let space = line_length != 0 && previous != "(" && token != ")";
+            if line_length != 0 && line_length + space as usize + token.len() > MAX_LINE_LENGTH {
+                pgn.push('\n');
+                line_length = 0;
+            }
+            else if space {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            pgn.push_str(&token);
+            previous = token;
+        }
+        pgn.push('\n');
+        pgn
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_pgn())
+    }
+}
+
+//parses every game in a pgn file
+pub fn parse_pgn(pgn: &str) -> Result<Vec<Game>, PgnError> {
+    let mut parser = Parser::new(pgn);
+    let mut games = vec![];
+    while let Some(game) = parser.parse_game()? {
+        games.push(game);
+    }
+    Ok(games)
+}
+
+fn result_token(result: Option<GameResult>) -> &'static str {
+    result.map_or("*", |result| result.as_str())
+}
+
+//the termination can only be told from the tag when it is not "normal", otherwise it has to come from the final position
+fn termination(tag: Option<&str>, result: Option<GameResult>, position: &History) -> Option<Termination> {
+    match tag {
+        Some("time forfeit") => Some(Termination::Timeout),
+        Some("adjudication") => Some(Termination::Adjudication),
+        _ => position.outcome().filter(|outcome| Some(outcome.result) == result).map(|outcome| outcome.termination)
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+//a comment ends at the first }, so it can not be written inside the comment and is left out
+fn comment_token(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ""))
+}
+
+//splits the moves into tokens, the move number is repeated for black after comments and variations
+//a move number is in the same token as its move so a line is never wrapped between them
+fn write_moves(tokens: &mut Vec<String>, moves: &[PgnMove], mut w_turn: bool, mut move_number: u32) {
+    let mut write_number = true;
+    for m in moves {
+        if let Some(comment) = &m.starting_comment {
+            tokens.push(comment_token(comment));
+            write_number = true;
+        }
+        if w_turn {
+            tokens.push(format!("{}. {}", move_number, m.san));
+        }
+        else if write_number {
+            tokens.push(format!("{}... {}", move_number, m.san));
+        }
+        else {
+            tokens.push(m.san.clone());
+        }
+        write_number = false;
+        for nag in &m.nags {
+            tokens.push(format!("${}", nag));
+        }
+        if let Some(comment) = &m.comment {
+            tokens.push(comment_token(comment));
+            write_number = true;
+        }
+        for variation in &m.variations {
+            tokens.push("(".to_string());
+            write_moves(tokens, variation, w_turn, move_number);
+            tokens.push(")".to_string());
+            write_number = true;
+        }
+        if !w_turn {
+            move_number += 1;
+        }
+        w_turn = !w_turn;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    TagOpen,
+    TagClose,
+    String(String),
+    Symbol(String), //moves, move numbers, tag names and results
+    Period,
+    Comment(String),
+    Nag(u8),
+    VariationOpen,
+    VariationClose,
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    peeked: Option<Token>,
+    line_start: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(pgn: &'a str) -> Parser<'a> {
+        Parser {chars: pgn.chars().peekable(), peeked: None, line_start: true}
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, PgnError> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_token()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next(&mut self) -> Result<Option<Token>, PgnError> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.read_token()
+        }
+    }
+
+    fn read_token(&mut self) -> Result<Option<Token>, PgnError> {
+        loop {
+            let c = match self.chars.next() {
+                Some(c) => c,
+                None => return Ok(None)
+            };
+            let line_start = self.line_start;
+            self.line_start = c == '\n';
+            match c {
+                //lines starting with % are escaped and ; starts a comment to the end of the line
+                '%' if line_start => self.skip_line(),
+                ';' => {
+                    let comment: String = std::iter::from_fn(|| self.chars.next_if(|c| *c != '\n')).collect();
+                    return Ok(Some(Token::Comment(comment.trim().to_string())))
+                },
+                c if c.is_whitespace() => {},
+                '[' => return Ok(Some(Token::TagOpen)),
+                ']' => return Ok(Some(Token::TagClose)),
+                '(' => return Ok(Some(Token::VariationOpen)),
+                ')' => return Ok(Some(Token::VariationClose)),
+                '.' => return Ok(Some(Token::Period)),
+                '*' => return Ok(Some(Token::Symbol("*".to_string()))),
+                '{' => {
+                    let mut comment = String::new();
+                    loop {
+                        match self.chars.next() {
+                            Some('}') => break,
+                            Some(c) => comment.push(c),
+                            None => return Err(PgnError::UnclosedComment)
+                        }
+                    }
+                    return Ok(Some(Token::Comment(comment.split_whitespace().collect::<Vec<&str>>().join(" "))))
+                },
+                '"' => {
+                    let mut string = String::new();
+                    loop {
+                        match self.chars.next() {
+                            Some('\\') => string.extend(self.chars.next()),
+                            Some('"') => break,
+                            Some(c) => string.push(c),
+                            None => return Err(PgnError::UnclosedString)
+                        }
+                    }
+                    return Ok(Some(Token::String(string)))
+                },
+                '$' => {
+                    let digits: String = std::iter::from_fn(|| self.chars.next_if(|c| c.is_ascii_digit())).collect();
+                    return match digits.parse() {
+                        Ok(nag) => Ok(Some(Token::Nag(nag))),
+                        Err(_) => Err(PgnError::UnexpectedToken(format!("${}", digits)))
+                    }
+                },
+                '!' | '?' => {
+                    let mut suffix = c.to_string();
+                    suffix.extend(std::iter::from_fn(|| self.chars.next_if(|c| *c == '!' || *c == '?')));
+                    let nag = match suffix.as_str() {
+                        "!" => 1,
+                        "?" => 2,
+                        "!!" => 3,
+                        "??" => 4,
+                        "!?" => 5,
+                        "?!" => 6,
+                        _ => return Err(PgnError::UnexpectedToken(suffix))
+                    };
+                    return Ok(Some(Token::Nag(nag)))
+                },
+                c if c.is_ascii_alphanumeric() => {
+                    let mut symbol = c.to_string();
+                    symbol.extend(std::iter::from_fn(|| self.chars.next_if(|c| c.is_ascii_alphanumeric() || "_+#=:-/".contains(*c))));
+                    return Ok(Some(Token::Symbol(symbol)))
+                },
+                c => return Err(PgnError::UnexpectedToken(c.to_string()))
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while self.chars.next_if(|c| *c != '\n').is_some() {}
+    }
+
+    //returns None when there are no more games
+    fn parse_game(&mut self) -> Result<Option<Game>, PgnError> {
+        if self.peek()?.is_none() {
+            return Ok(None)
+        }
+
+        let mut tags = vec![];
+        while self.peek()? == Some(&Token::TagOpen) {
+            self.next()?;
+            match (self.next()?, self.next()?, self.next()?) {
+                (Some(Token::Symbol(name)), Some(Token::String(value)), Some(Token::TagClose)) => tags.push((name, value)),
+                (name, value, _) => return Err(PgnError::InvalidTag(format!("{:?} {:?}", name, value)))
+            }
+        }
+
+        let mut game = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Game::from_position(Position::from_fen(fen).map_err(PgnError::InvalidFen)?),
+            None => Game::new()
+        };
+        game.tags = tags;
+        game.result = match game.tag("Result") {
+            Some("1-0") => Some(GameResult::WhiteWin),
+            Some("0-1") => Some(GameResult::BlackWin),
+            Some("1/2-1/2") => Some(GameResult::Draw),
+            _ => None
+        };
+
+        if let Some(Token::Comment(_)) = self.peek()? {
+            if let Some(Token::Comment(comment)) = self.next()? {
+                game.comment = Some(comment);
+            }
+        }
+        let mut position = game.position.clone();
+        let (moves, result) = self.parse_moves(&mut position, false)?;
+        game.moves = moves;
+        game.position = position;
+        if let Some(result) = result {
+            game.result = result;
+        }
+        game.termination = termination(game.tag("Termination"), game.result, &game.position);
+        Ok(Some(game))
+    }
+
+    //parses moves until the end of the variation or game, the moves are played on position
+    //returns the moves and the result if the line ended with a result token
+    #[allow(clippy::type_complexity)]
+    fn parse_moves(&mut self, position: &mut History, variation: bool) -> Result<(Vec<PgnMove>, Option<Option<GameResult>>), PgnError> {
+        let mut moves: Vec<PgnMove> = vec![];
+        //a comment before the first move is kept until the move is read
+        let mut starting_comment: Option<String> = None;
+        loop {
+            let token = match self.next()? {
+                Some(token) => token,
+                None if variation => return Err(PgnError::UnbalancedVariation),
+                None => return Ok((moves, None))
+            };
+            match token {
+                Token::Period => {},
+                Token::Symbol(symbol) => {
+                    let result = match symbol.as_str() {
+                        "1-0" => Some(Some(GameResult::WhiteWin)),
+                        "0-1" => Some(Some(GameResult::BlackWin)),
+                        "1/2-1/2" => Some(Some(GameResult::Draw)),
+                        "*" => Some(None),
+                        _ => None
+                    };
+                    if result.is_some() {
+                        if variation {
+                            return Err(PgnError::UnbalancedVariation)
+                        }
+                        return Ok((moves, result))
+                    }
+                    if symbol.bytes().all(|byte| byte.is_ascii_digit()) {
+                        continue; //move number
+                    }
+                    let illegal = |error| PgnError::IllegalMove{ply: moves.len() + 1, san: symbol.clone(), error};
+                    if !position.game_in_progress() {
+                        return Err(illegal(MoveError::GameOver))
+                    }
+                    let m = position.parse_san(&symbol).map_err(illegal)?;
+                    let uci = m.to_uci();
+                    let san = position.san(&m, &position.generate_moves());
+                    position.make_move(&uci);
+                    moves.push(PgnMove {san, uci, nags: vec![], starting_comment: starting_comment.take(), comment: None, variations: vec![]});
+                },
+                Token::Nag(nag) => match moves.last_mut() {
+                    Some(m) => m.nags.push(nag),
+                    None => return Err(PgnError::UnexpectedToken(format!("${}", nag)))
+                },
+                Token::Comment(comment) => {
+                    let existing = match moves.last_mut() {
+                        Some(m) => &mut m.comment,
+                        None => &mut starting_comment
+                    };
+                    *existing = Some(match existing.take() {
+                        Some(existing) => existing + " " + &comment,
+                        None => comment
+                    });
+                },
+                Token::VariationOpen => {
+                    //the variation is played from the position before the last move, then its moves are taken back
+                    let m = moves.last_mut().ok_or(PgnError::UnbalancedVariation)?;
+                    position.unmake_move();
+                    let (variation_moves, _) = self.parse_moves(position, true)?;
+                    for _ in &variation_moves {
+                        position.unmake_move();
+                    }
+                    position.make_move(&m.uci);
+                    m.variations.push(variation_moves);
+                },
+                Token::VariationClose if variation => return Ok((moves, None)),
+                Token::VariationClose => return Err(PgnError::UnbalancedVariation),
+                Token::TagOpen if !variation => {
+                    //the next game started without a result token
+                    self.peeked = Some(Token::TagOpen);
+                    return Ok((moves, None))
+                },
+                token => return Err(PgnError::UnexpectedToken(format!("{:?}", token)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Game, PgnError, parse_pgn};
+    use crate::{GameResult, MoveError, Outcome, Termination};
+
+    const GAME: &str = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5
+Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6
+23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5
+hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5
+35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6
+Nf2 42. g4 Bd3 43. Re6 1/2-1/2
+"#;
+
+    #[test]
+    fn parse_game() {
+        let game = Game::from_pgn(GAME).unwrap();
+        assert_eq!(game.tag("White"), Some("Fischer, Robert J."));
+        assert_eq!(game.result(), Some(GameResult::Draw));
+        assert_eq!(game.moves().len(), 85);
+        assert_eq!(game.moves()[8].uci, "e1g1");
+        assert_eq!(game.moves()[4].comment.as_deref(), Some("This opening is called the Ruy Lopez."));
+        assert_eq!(game.position().to_fen(), "8/8/4R1p1/2k3p1/1p4P1/1P1b1P2/3K1n2/8 b - - 2 43");
+        assert_eq!(game.positions().len(), 86);
+
+        //writing and reading the game again gives the same game
+        let pgn = game.to_pgn();
+        assert!(pgn.lines().all(|line| line.len() < 80));
+        //a move number is never split from its move
+        assert!(pgn.ends_with("\n42. g4 Bd3 43. Re6 1/2-1/2\n"));
+        assert!(pgn.lines().all(|line| !line.ends_with('.')));
+        let reparsed = Game::from_pgn(&pgn).unwrap();
+        assert_eq!(reparsed.moves(), game.moves());
+        assert_eq!(reparsed.tags(), game.tags());
+    }
+
+    #[test]
+    fn annotations_and_variations() {
+        let pgn = "{start} 1. e4! $14 (1. d4 d5 (1... Nf6 2. c4) 2. c4 {queens gambit}) 1... e5?! 2. Nf3 ; rest of line comment\n Nc6 *";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.comment(), Some("start"));
+        assert_eq!(game.result(), None);
+        assert_eq!(game.moves().len(), 4);
+        assert_eq!(game.moves()[0].nags, vec![1, 14]);
+        assert_eq!(game.moves()[1].nags, vec![6]);
+        assert_eq!(game.moves()[2].comment.as_deref(), Some("rest of line comment"));
+        let variation = &game.moves()[0].variations[0];
+        assert_eq!(variation.len(), 3);
+        assert_eq!(variation[1].variations[0][0].san, "Nf6");
+        assert_eq!(variation[2].comment.as_deref(), Some("queens gambit"));
+
+        let written = game.to_pgn();
+        assert!(written.ends_with("{start} 1. e4 $1 $14 (1. d4 d5 (1... Nf6 2. c4) 2. c4 {queens gambit}) 1... e5\n$6 2. Nf3 {rest of line comment} 2... Nc6 *\n"));
+        let reparsed = Game::from_pgn(&written).unwrap();
+        assert_eq!(reparsed.moves(), game.moves());
+
+        //comments before the first move of a variation are kept and a } can not end up inside a comment
+        let mut game = Game::from_pgn("1. e4 ({closed} 1. d4 {queens pawn}) e5 *").unwrap();
+        assert_eq!(game.moves()[0].variations[0][0].starting_comment.as_deref(), Some("closed"));
+        game.comment_last_move("a smiley :}");
+        let written = game.to_pgn();
+        assert!(written.ends_with("1. e4 ({closed} 1. d4 {queens pawn}) 1... e5 {a smiley :} *\n"));
+        assert_eq!(Game::from_pgn(&written).unwrap().moves()[1].comment.as_deref(), Some("a smiley :"));
+    }
+
+    #[test]
+    fn several_games_and_errors() {
+        let games = parse_pgn("[Event \"a\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n\n[Event \"b\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. O-O Kd7 1-0\n").unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].result(), Some(GameResult::BlackWin));
+        assert_eq!(games[0].moves()[3].san, "Qh4#");
+        assert_eq!(games[1].moves()[0].uci, "e1g1");
+        assert!(games[1].to_pgn().contains("[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]"));
+
+        assert_eq!(Game::from_pgn("1. e4 e5 2. Ke3 *").err(), Some(PgnError::IllegalMove{ply: 3, san: "Ke3".to_string(), error: MoveError::IllegalMove}));
+        assert_eq!(Game::from_pgn("1. e4 {never closed").err(), Some(PgnError::UnclosedComment));
+        assert_eq!(Game::from_pgn("[Event \"never closed]\n\n1. e4 *").err(), Some(PgnError::UnclosedString));
+        assert_eq!(Game::from_pgn("1. e4 (1. d4 *").err(), Some(PgnError::UnbalancedVariation));
+        assert_eq!(Game::from_pgn("").err(), Some(PgnError::Empty));
+    }
+
+    #[test]
+    fn write_game() {
+        let mut game = Game::new();
+        game.set_tag("White", "linug");
+        assert_eq!(game.push_uci("e2e4"), Ok(()));
+        assert_eq!(game.push_san("e5"), Ok(()));
+        assert_eq!(game.push_san("Ke3"), Err(MoveError::IllegalMove));
+        assert_eq!(game.push_uci("g1f3"), Ok(()));
+        game.comment_last_move("developing");
+        game.set_result(Some(GameResult::WhiteWin));
+        assert_eq!(game.to_pgn(), "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"linug\"]\n[Black \"?\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 {developing} 1-0\n");
+    }
+    #[test]
+    fn termination() {
+        //the game ends by itself when it is checkmate
+        let mut game = Game::new();
+        for m in ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"] {
+            assert_eq!(game.push_san(m), Ok(()));
+        }
+        assert_eq!(game.outcome(), Some(Outcome {result: GameResult::WhiteWin, termination: Termination::Checkmate}));
+        assert!(game.to_pgn().contains("[Result \"1-0\"]\n[Termination \"normal\"]\n"));
+        let game = Game::from_pgn(&game.to_pgn()).unwrap();
+        assert_eq!(game.termination(), Some(Termination::Checkmate));
+
+        let mut game = Game::new();
+        game.push_san("e4").unwrap();
+        game.set_outcome(Some(Outcome {result: GameResult::WhiteWin, termination: Termination::Timeout}));
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[Termination \"time forfeit\"]"));
+        assert!(pgn.ends_with("1. e4 1-0\n"));
+        assert_eq!(Game::from_pgn(&pgn).unwrap().outcome(), game.outcome());
+        game.set_result(None);
+        assert_eq!(game.outcome(), None);
+        assert!(!game.to_pgn().contains("Termination"));
+
+        //a normal ending that is not over by the rules, like a resignation, can not be told from the tag
+        let game = Game::from_pgn("[Result \"0-1\"]\n[Termination \"normal\"]\n\n1. e4 0-1").unwrap();
+        assert_eq!(game.termination(), None);
+        assert!(game.to_pgn().contains("[Termination \"normal\"]"));
+    }
+
+}
@@ -0,0 +1,301 @@
+//standard algebraic notation, the notation used by pgn files and most humans
+//example Nf3, exd5, O-O, e8=Q+
+//https://www.chessprogramming.org/Algebraic_Chess_Notation#Standard_Algebraic_Notation_.28SAN.29
+
+use crate::{Move, MoveError, Position, SQUARE_NAME, is_castling};
+use crate::move_list::MoveList;
+use crate::Role::{self, *};
+
+impl Position {
+    //converts a legal move in uci format to san
+    pub fn uci_to_san(&self, m: &str) -> Result<String, MoveError> {
+        self.move_to_san(self.uci_to_move(m)?)
+    }
+
+    //writes a typed move in san, returns an error if the move is not legal in this position
+    pub fn move_to_san(&self, m: Move) -> Result<String, MoveError> {
+        let legal_moves = self.generate_moves();
+        if !legal_moves.contains(&m) {
+            return Err(MoveError::IllegalMove)
+        }
+        Ok(self.san(&m, &legal_moves))
+    }
+
+    //finds the legal move written in san
+    pub fn san_to_move(&self, san: &str) -> Result<Move, MoveError> {
+        self.parse_san(san)
+    }
+
+    //converts a move in san to uci format, the move has to be legal
+    pub fn san_to_uci(&self, san: &str) -> Result<String, MoveError> {
+        Ok(self.parse_san(san)?.to_uci())
+    }
+
+    //returns all legal moves in san
+    pub fn get_legal_moves_san(&self) -> Vec<String> {
+        let legal_moves = self.generate_moves();
+        legal_moves.iter().map(|m| self.san(m, &legal_moves)).collect()
+    }
+
+    //plays a move written in san, returns an error if the move is not valid san or is not legal
+    pub fn try_make_san_move(&mut self, san: &str) -> Result<(), MoveError> {
+        let m = self.checked_san_to_move(san)?;
+        self.play(m);
+        Ok(())
+    }
+
+    //the move try_make_san_move plays, with the same errors
+    pub(crate) fn checked_san_to_move(&self, san: &str) -> Result<Move, MoveError> {
+        if !self.game_in_progress() {
+            return Err(MoveError::GameOver)
+        }
+        let m = self.parse_san(san)?;
+        if self.captured_piece(&m) == Some(King) {
+            return Err(MoveError::IllegalMove)
+        }
+        Ok(m)
+    }
+
+    //the move has to be one of the legal moves, it is made on a copy of the position to find check and mate
+    pub(crate) fn san(&self, m: &Move, legal_moves: &MoveList) -> String {
+        let mut san = String::new();
+        if is_castling(m) {
+            san.push_str(if (m.from >> 2) == m.destination {"O-O"} else {"O-O-O"});
+        }
+        else {
+            let from_name = SQUARE_NAME[m.from.trailing_zeros() as usize].as_bytes();
+            let capture = if self.w_turn {self.b_all} else {self.w_all} & m.destination != 0
+                || (m.piece == Pawn && m.destination == self.en_passent_target_square);
+            if m.piece == Pawn {
+                if capture {
+                    san.push(from_name[0] as char);
+                }
+            }
+            else {
+                san.push(piece_letter(m.piece));
+                //other pieces of the same kind that can move to the same square
+                let others: Vec<&[u8]> = legal_moves.iter()
+                    .filter(|other| other.piece == m.piece && other.destination == m.destination && other.from != m.from)
+                    .map(|other| SQUARE_NAME[other.from.trailing_zeros() as usize].as_bytes())
+                    .collect();
+                if !others.is_empty() {
+                    if others.iter().all(|other| other[0] != from_name[0]) {
+                        san.push(from_name[0] as char);
+                    }
+                    else if others.iter().all(|other| other[1] != from_name[1]) {
+                        san.push(from_name[1] as char);
+                    }
+                    else {
+                        san.push(from_name[0] as char);
+                        san.push(from_name[1] as char);
+                    }
+                }
+            }
+            if capture {
+                san.push('x');
+            }
+            san.push_str(SQUARE_NAME[m.destination.trailing_zeros() as usize]);
+            if let Some(promotion) = m.promotion {
+                san.push('=');
+                san.push(piece_letter(promotion));
+            }
+        }
+
+        let mut after = *self;
+        after.play(*m);
+        if after.in_check() {
+            san.push(if after.generate_moves().is_empty() {'#'} else {'+'});
+        }
+        san
+    }
+
+    //finds the legal move a san string refers to, check and mate suffixes and annotations like ! and ? are ignored
+    pub(crate) fn parse_san(&self, san: &str) -> Result<Move, MoveError> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        if !san.is_ascii() || san.len() < 2 {
+            return Err(MoveError::InvalidSan)
+        }
+
+        if san == "O-O" || san == "0-0" || san == "O-O-O" || san == "0-0-0" {
+            let kingside = san.len() == 3;
+            return self.generate_moves().iter()
+                .find(|m| is_castling(m) && ((m.from >> 2) == m.destination) == kingside)
+                .copied()
+                .ok_or(MoveError::IllegalMove)
+        }
+
+        let mut bytes = san.as_bytes();
+        let piece = match bytes[0] {
+            b'K' => King,
+            b'Q' => Queen,
+            b'R' => Rook,
+            b'B' => Bishop,
+            b'N' => Knight,
+            _ => Pawn
+        };
+        if piece != Pawn {
+            bytes = &bytes[1..];
+        }
+
+        //promotions are written e8=Q, e8Q is also accepted
+        let mut promotion = None;
+        if let Some(last) = bytes.last() {
+            let promotion_piece = match last {
+                b'Q' => Some(Queen),
+                b'R' => Some(Rook),
+                b'B' => Some(Bishop),
+                b'N' => Some(Knight),
+                _ => None
+            };
+            if promotion_piece.is_some() {
+                if piece != Pawn {
+                    return Err(MoveError::InvalidSan)
+                }
+                promotion = promotion_piece;
+                bytes = &bytes[..bytes.len() - 1];
+                if bytes.last() == Some(&b'=') {
+                    bytes = &bytes[..bytes.len() - 1];
+                }
+            }
+        }
+
+        if bytes.len() < 2 {
+            return Err(MoveError::InvalidSan)
+        }
+        let (disambiguation, destination_name) = bytes.split_at(bytes.len() - 2);
+        let destination = match std::str::from_utf8(destination_name).ok().and_then(crate::square_from_name) {
+            Some(destination) => destination,
+            None => return Err(MoveError::InvalidSan)
+        };
+
+        //what is left is the optional from file and rank and the capture sign
+        let mut from_file = None;
+        let mut from_rank = None;
+        for byte in disambiguation {
+            match byte {
+                b'a'..=b'h' if from_file.is_none() && from_rank.is_none() => from_file = Some(*byte),
+                b'1'..=b'8' if from_rank.is_none() => from_rank = Some(*byte),
+                b'x' => {},
+                _ => return Err(MoveError::InvalidSan)
+            }
+        }
+
+        //a pawn move without a file is a push so it stays on the destination file
+        if piece == Pawn && from_file.is_none() {
+            from_file = Some(destination_name[0]);
+        }
+
+        let legal_moves = self.generate_moves();
+        let mut candidates = legal_moves.iter().filter(|m| {
+            let from_name = SQUARE_NAME[m.from.trailing_zeros() as usize].as_bytes();
+            m.piece == piece && m.destination == destination && m.promotion == promotion && !is_castling(m)
+                && from_file.is_none_or(|file| file == from_name[0])
+                && from_rank.is_none_or(|rank| rank == from_name[1])
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(m), None) => Ok(*m),
+            (None, _) => Err(MoveError::IllegalMove),
+            (Some(_), Some(_)) => Err(MoveError::AmbiguousSan)
+        }
+    }
+}
+
+//pawns have no letter in san, it is only asked for a piece that is not a pawn or a promotion which is never a pawn
+fn piece_letter(piece: Role) -> char {
+    match piece {
+        King => 'K',
+        Queen => 'Q',
+        Rook => 'R',
+        Bishop => 'B',
+        Knight => 'N',
+        Pawn => unreachable!("a pawn has no letter in san")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Position, MoveError};
+
+    #[test]
+    fn san_output() {
+        let mut pos = Position::startpos();
+        assert_eq!(pos.uci_to_san("e2e4"), Ok("e4".to_string()));
+        assert_eq!(pos.uci_to_san("g1f3"), Ok("Nf3".to_string()));
+        assert_eq!(pos.uci_to_san("e2e5"), Err(MoveError::IllegalMove));
+
+        for m in ["e2e4", "e7e5", "d1h5", "b8c6", "f1c4", "g8f6"] {
+            pos.make_move(m);
+        }
+        assert_eq!(pos.uci_to_san("h5f7"), Ok("Qxf7#".to_string()));
+        assert_eq!(pos.uci_to_san("c4f7"), Ok("Bxf7+".to_string()));
+
+        //castling and en passant
+        let pos = Position::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        assert_eq!(pos.uci_to_san("e1g1"), Ok("O-O".to_string()));
+        assert_eq!(pos.uci_to_san("e1c1"), Ok("O-O-O".to_string()));
+        assert_eq!(pos.uci_to_san("e5d6"), Ok("exd6".to_string()));
+        assert_eq!(pos.uci_to_san("a1a8"), Ok("Rxa8+".to_string()));
+
+        //disambiguation by file, by rank and by both
+        let pos = Position::from_fen("k7/8/8/8/1N3N2/8/1N6/K7 w - - 0 1").unwrap();
+        assert_eq!(pos.uci_to_san("f4d3"), Ok("Nfd3".to_string()));
+        assert_eq!(pos.uci_to_san("b2d3"), Ok("N2d3".to_string()));
+        assert_eq!(pos.uci_to_san("b4d5"), Ok("Nbd5".to_string()));
+        assert_eq!(pos.uci_to_san("b4a6"), Ok("Na6".to_string()));
+        let pos = Position::from_fen("2k5/8/8/8/3Q3Q/8/7Q/K7 w - - 0 1").unwrap();
+        assert_eq!(pos.uci_to_san("h4f2"), Ok("Qh4f2".to_string()));
+        assert_eq!(pos.uci_to_san("h2f2"), Ok("Q2f2".to_string()));
+        assert_eq!(pos.uci_to_san("d4f2"), Ok("Qdf2".to_string()));
+
+        //promotions
+        let pos = Position::from_fen("2k5/4P3/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(pos.uci_to_san("e7e8q"), Ok("e8=Q+".to_string()));
+        assert_eq!(pos.uci_to_san("e7e8n"), Ok("e8=N".to_string()));
+    }
+
+    #[test]
+    fn san_input() {
+        let pos = Position::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        assert_eq!(pos.san_to_uci("O-O"), Ok("e1g1".to_string()));
+        assert_eq!(pos.san_to_uci("0-0-0"), Ok("e1c1".to_string()));
+        assert_eq!(pos.san_to_uci("exd6"), Ok("e5d6".to_string()));
+        assert_eq!(pos.san_to_uci("Rxa8+"), Ok("a1a8".to_string()));
+        assert_eq!(pos.san_to_uci("Ra8"), Ok("a1a8".to_string()));
+        assert_eq!(pos.san_to_uci("Rb1"), Ok("a1b1".to_string()));
+        assert_eq!(pos.san_to_uci("Rhf1"), Ok("h1f1".to_string()));
+        assert_eq!(pos.san_to_uci("Nf3"), Err(MoveError::IllegalMove));
+        assert_eq!(pos.san_to_uci("Zf3"), Err(MoveError::InvalidSan));
+        assert_eq!(pos.san_to_uci("e9"), Err(MoveError::InvalidSan));
+        assert_eq!(pos.san_to_uci(""), Err(MoveError::InvalidSan));
+
+        let pos = Position::from_fen("k7/8/8/8/1N3N2/8/1N6/K7 w - - 0 1").unwrap();
+        assert_eq!(pos.san_to_uci("Nd3"), Err(MoveError::AmbiguousSan));
+        assert_eq!(pos.san_to_uci("Nbd3"), Err(MoveError::AmbiguousSan));
+        assert_eq!(pos.san_to_uci("Nfd3"), Ok("f4d3".to_string()));
+        assert_eq!(pos.san_to_uci("Nb4d3"), Ok("b4d3".to_string()));
+
+        let pos = Position::from_fen("2k5/4P3/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(pos.san_to_uci("e8=Q+"), Ok("e7e8q".to_string()));
+        assert_eq!(pos.san_to_uci("e8N"), Ok("e7e8n".to_string()));
+        assert_eq!(pos.san_to_uci("e8"), Err(MoveError::IllegalMove));
+    }
+
+    //every legal move has to survive a trip through san
+    #[test]
+    fn san_round_trip() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            let pos = Position::from_fen(fen).unwrap();
+            for m in pos.get_legal_moves() {
+                let san = pos.uci_to_san(&m).unwrap();
+                assert_eq!(pos.san_to_uci(&san), Ok(m));
+            }
+            for &m in &pos.legal_moves() {
+                assert_eq!(pos.san_to_move(&pos.move_to_san(m).unwrap()), Ok(m));
+            }
+        }
+    }
+}
@@ -0,0 +1,492 @@
+//negamax alpha-beta search with iterative deepening and quiescence search
+//https://www.chessprogramming.org/Negamax
+//https://www.chessprogramming.org/Iterative_Deepening
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{Move, Position};
+use crate::history::History;
+use crate::move_list::MoveList;
+use crate::transposition::{Bound, TranspositionTable};
+
+pub const MAX_DEPTH: u8 = 64;
+
+//a mate found at ply n is scored MATE - n so shorter mates score higher
+const MATE: i32 = 30000;
+const INFINITY: i32 = 32000;
+
+//the stop flag, node count and clock are only checked every this many nodes
+const CHECK_INTERVAL: u64 = 1024;
+
+const PIECE_VALUE: [i32; 6] = [0, 900, 330, 320, 500, 100]; //indexed by Role, the king is never captured
+
+//piece square tables from white's point of view, the first entry is a8 and the last is h1
+//https://www.chessprogramming.org/Simplified_Evaluation_Function
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+const KING_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+const PIECE_TABLE: [&[i32; 64]; 6] = [&KING_TABLE, &QUEEN_TABLE, &BISHOP_TABLE, &KNIGHT_TABLE, &ROOK_TABLE, &PAWN_TABLE];
+
+//limits for the search, it stops at whichever limit is reached first
+//with no limits set the search runs to MAX_DEPTH or until the stop flag is set
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub stop: Option<Arc<AtomicBool>>, //set to true from another thread to stop the search
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    Mate(i32), //moves until mate, negative if the side to move is getting mated
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>, //None if there are no legal moves
+    pub score: Score, //from the point of view of the side to move
+    pub depth: u8, //the last fully searched depth
+    pub nodes: u64,
+    pub pv: Vec<Move>, //the principal variation, starting with the best move
+}
+
+impl Position {
+    //searches for the best move within the limits
+    pub fn best_move(&self, limits: SearchLimits) -> SearchResult {
+        self.search(limits, |_| {})
+    }
+
+    //like best_move but calls on_iteration with the result of every finished depth
+    //a new transposition table is made for the search, use search_with_table to keep one between searches
+    pub fn search(&self, limits: SearchLimits, on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+        self.search_with_table(limits, &TranspositionTable::default(), on_iteration)
+    }
+
+    //searches using the given transposition table, it can be shared with other searches running at the same time
+    //the position does not know the moves before it, search a History to also avoid repeating earlier positions of the game
+    pub fn search_with_table(&self, limits: SearchLimits, table: &TranspositionTable, on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+        search(self, vec![], limits, table, on_iteration)
+    }
+}
+
+impl History {
+    //like Position.best_move but positions earlier in the game count as repetitions
+    pub fn best_move(&self, limits: SearchLimits) -> SearchResult {
+        self.search(limits, |_| {})
+    }
+
+    pub fn search(&self, limits: SearchLimits, on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+        self.search_with_table(limits, &TranspositionTable::default(), on_iteration)
+    }
+
+    pub fn search_with_table(&self, limits: SearchLimits, table: &TranspositionTable, on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+        search(self.position(), self.previous_hashes(), limits, table, on_iteration)
+    }
+}
+
+//hashes are the zobrist keys of the positions played before the position, used to find repetitions
+fn search(position: &Position, hashes: Vec<u64>, limits: SearchLimits, table: &TranspositionTable, mut on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+    table.new_search();
+    let mut searcher = Searcher {limits: &limits, table, start: Instant::now(), nodes: 0, stopped: false, previous_pv: vec![], hashes};
+    let legal_moves = position.generate_moves();
+    let mut result = SearchResult {
+        best_move: legal_moves.first().copied(),
+        score: Score::Centipawns(0),
+        depth: 0,
+        nodes: 0,
+        pv: vec![],
+    };
+    if legal_moves.is_empty() {
+        result.score = if position.in_check() {Score::Mate(0)} else {Score::Centipawns(0)};
+        return result
+    }
+
+    //the moves are played and unmade on one copy of the position
+    let mut position = *position;
+    let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+    for depth in 1..=max_depth {
+        let mut pv = vec![];
+        let score = searcher.negamax(&mut position, depth, 0, -INFINITY, INFINITY, &mut pv, true);
+        //an unfinished iteration is thrown away
+        if searcher.stopped {
+            break
+        }
+        result = SearchResult {
+            best_move: pv.first().copied(),
+            score: to_score(score),
+            depth,
+            nodes: searcher.nodes,
+            pv: pv.clone(),
+        };
+        on_iteration(&result);
+        searcher.previous_pv = pv;
+        //there is no need to search deeper when a forced mate has been found
+        if score.abs() >= MATE - depth as i32 {
+            break
+        }
+    }
+    result.nodes = searcher.nodes;
+    result
+}
+
+fn to_score(score: i32) -> Score {
+    if score >= MATE - MAX_DEPTH as i32 {
+        Score::Mate((MATE - score + 1) / 2)
+    }
+    else if score <= -MATE + MAX_DEPTH as i32 {
+        Score::Mate(-(MATE + score) / 2)
+    }
+    else {
+        Score::Centipawns(score)
+    }
+}
+
+//mate scores are stored relative to the node instead of the root, so they stay correct when the position is reached at another ply
+fn score_to_table(score: i32, ply: u8) -> i16 {
+    if score >= MATE - MAX_DEPTH as i32 * 2 {
+        (score + ply as i32) as i16
+    }
+    else if score <= -MATE + MAX_DEPTH as i32 * 2 {
+        (score - ply as i32) as i16
+    }
+    else {
+        score as i16
+    }
+}
+
+fn score_from_table(score: i16, ply: u8) -> i32 {
+    let score = score as i32;
+    if score >= MATE - MAX_DEPTH as i32 * 2 {
+        score - ply as i32
+    }
+    else if score <= -MATE + MAX_DEPTH as i32 * 2 {
+        score + ply as i32
+    }
+    else {
+        score
+    }
+}
+
+struct Searcher<'a> {
+    limits: &'a SearchLimits,
+    table: &'a TranspositionTable,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+    previous_pv: Vec<Move>, //the pv of the last iteration, searched first in the next one
+    hashes: Vec<u64>, //the zobrist keys of the positions before the current node, in the game and in the search
+}
+
+impl Searcher<'_> {
+    //the first depth is always finished so there is a move to play, previous_pv is only empty while it runs
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && !self.previous_pv.is_empty() && self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.stopped = self.limits.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed))
+                || self.limits.movetime.is_some_and(|movetime| self.start.elapsed() >= movetime)
+                || self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes);
+        }
+        self.stopped
+    }
+
+    //a position that occurred before since the last capture or pawn move, with the same side to move
+    fn repeated(&self, position: &Position) -> bool {
+        self.hashes.iter().rev()
+            .take(position.halfmove_clock as usize)
+            .skip(1)
+            .step_by(2)
+            .any(|hash| *hash == position.hash)
+    }
+
+    //returns the score from the point of view of the side to move, pv is filled with the best line
+    //follow_pv is true while the node is on the pv of the previous iteration
+    //the position is the same as before when this returns, every move is unmade after it is searched
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(&mut self, position: &mut Position, depth: u8, ply: u8, mut alpha: i32, beta: i32, pv: &mut Vec<Move>, follow_pv: bool) -> i32 {
+        self.nodes += 1;
+        if ply > 0 && self.should_stop() {
+            return 0
+        }
+        let legal_moves = position.generate_moves();
+        if legal_moves.is_empty() {
+            return if position.in_check() {-MATE + ply as i32} else {0}
+        }
+        //the opponent can claim a draw so it is scored as one
+        //a position that has occurred before is also a draw, since repeating it again can not be better
+        //and so is a position where neither side can mate
+        if ply > 0 && (position.halfmove_clock >= 100 || self.repeated(position) || position.insufficient_material()) {
+            return 0
+        }
+        if depth == 0 || ply >= MAX_DEPTH {
+            return self.quiescence(position, alpha, beta)
+        }
+
+        let key = position.hash();
+        let entry = self.table.probe(key);
+        let table_move = entry.and_then(|entry| entry.best_move(position));
+        //the root always searches so there is a pv to return
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = score_from_table(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                if let Some(m) = table_move {
+                    pv.push(m);
+                }
+                return score.clamp(alpha, beta)
+            }
+        }
+
+        let pv_move = if follow_pv {self.previous_pv.get(ply as usize).cloned()} else {None};
+        let moves = order_moves(position, legal_moves, pv_move.as_ref().or(table_move.as_ref()));
+        let original_alpha = alpha;
+        let mut best_move = None;
+        let mut child_pv = vec![];
+        self.hashes.push(key);
+        for (i, &m) in moves.iter().enumerate() {
+            let undo = position.play(m);
+            child_pv.clear();
+            let score = -self.negamax(position, depth - 1, ply + 1, -beta, -alpha, &mut child_pv, follow_pv && i == 0 && pv_move.is_some());
+            position.unmake_move(undo);
+            if self.stopped {
+                self.hashes.pop();
+                return 0
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(m);
+                pv.clear();
+                pv.push(m);
+                pv.append(&mut child_pv);
+                if alpha >= beta {
+                    break
+                }
+            }
+        }
+        self.hashes.pop();
+
+        let bound = if alpha >= beta {Bound::Lower} else if alpha > original_alpha {Bound::Exact} else {Bound::Upper};
+        self.table.store(key, depth, bound, score_to_table(alpha, ply), best_move.as_ref());
+        alpha
+    }
+
+    //only captures and promotions are searched so the evaluation is not done in the middle of an exchange
+    //https://www.chessprogramming.org/Quiescence_Search
+    fn quiescence(&mut self, position: &mut Position, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        let stand_pat = evaluate(position);
+        if stand_pat >= beta {
+            return stand_pat
+        }
+        alpha = alpha.max(stand_pat);
+
+        let moves = order_moves(position, position.generate_moves(), None);
+        for &m in &moves {
+            if position.captured_piece(&m).is_none() && m.promotion.is_none() {
+                continue;
+            }
+            if self.should_stop() {
+                return 0
+            }
+            let undo = position.play(m);
+            let score = -self.quiescence(position, -beta, -alpha);
+            position.unmake_move(undo);
+            if score >= beta {
+                return score
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
+
+//the pv move is searched first, then captures with the most valuable victim and least valuable attacker first
+//https://www.chessprogramming.org/MVV-LVA
+fn order_moves(position: &Position, mut moves: MoveList, pv_move: Option<&Move>) -> MoveList {
+    moves.sort_by_key(|m| {
+        if Some(m) == pv_move {
+            return i32::MIN
+        }
+        let mut key = 0;
+        if let Some(captured) = position.captured_piece(m) {
+            key -= 10 * PIECE_VALUE[captured as usize] - PIECE_VALUE[m.piece as usize];
+        }
+        if let Some(promotion) = m.promotion {
+            key -= PIECE_VALUE[promotion as usize];
+        }
+        key
+    });
+    moves
+}
+
+//material and piece square tables from the point of view of the side to move
+fn evaluate(position: &Position) -> i32 {
+    let mut score = 0;
+    for piece in crate::PIECES {
+        let mut white = position.w_board[piece as usize];
+        while white != 0 {
+            let square = white.trailing_zeros() as usize;
+            white &= white - 1;
+            score += PIECE_VALUE[piece as usize] + PIECE_TABLE[piece as usize][63 - square];
+        }
+        let mut black = position.b_board[piece as usize];
+        while black != 0 {
+            let square = black.trailing_zeros() as usize;
+            black &= black - 1;
+            //the table is mirrored vertically for black
+            score -= PIECE_VALUE[piece as usize] + PIECE_TABLE[piece as usize][(63 - square) ^ 56];
+        }
+    }
+    if position.w_turn {score} else {-score}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{Score, SearchLimits};
+    use crate::Position;
+    use crate::transposition::TranspositionTable;
+
+    #[test]
+    fn finds_mate() {
+        let position = Position::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap();
+        let result = position.best_move(SearchLimits {depth: Some(3), ..Default::default()});
+        assert_eq!(result.best_move, Some(position.uci_to_move("h5f7").unwrap()));
+        assert_eq!(result.score, Score::Mate(1));
+        assert_eq!(result.pv, vec![position.uci_to_move("h5f7").unwrap()]);
+
+        //mate in two with a rook ladder
+        let position = Position::from_fen("6k1/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let result = position.best_move(SearchLimits {depth: Some(4), ..Default::default()});
+        assert_eq!(result.score, Score::Mate(2));
+        assert_eq!(result.pv.len(), 3);
+
+        //the side to move is mated
+        let position = Position::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let result = position.best_move(SearchLimits::default());
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, Score::Mate(0));
+    }
+
+    #[test]
+    fn wins_material() {
+        //the knight on d5 is hanging
+        let position = Position::from_fen("rnbqkb1r/pppp1ppp/8/3np3/8/2N5/PPPP1PPP/R1BQKBNR w KQkq - 0 4").unwrap();
+        let mut depths = vec![];
+        let result = position.search(SearchLimits {depth: Some(3), ..Default::default()}, |iteration| depths.push(iteration.depth));
+        assert_eq!(result.best_move, Some(position.uci_to_move("c3d5").unwrap()));
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!(result.depth, 3);
+        assert_eq!(result.pv[0].to_uci(), "c3d5");
+        assert!(result.nodes > 0);
+        match result.score {
+            Score::Centipawns(score) => assert!(score > 200),
+            Score::Mate(_) => panic!("there is no mate")
+        }
+    }
+
+    #[test]
+    fn node_limit() {
+        let result = Position::startpos().best_move(SearchLimits {nodes: Some(5000), ..Default::default()});
+        assert!(result.best_move.is_some());
+        assert!(result.depth >= 1);
+        assert!(result.nodes < 5000 + super::CHECK_INTERVAL);
+    }
+    #[test]
+    fn transposition_table() {
+        let position = Position::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+        let limits = SearchLimits {depth: Some(4), ..Default::default()};
+        let table = Arc::new(TranspositionTable::new(1));
+        let first = position.search_with_table(limits.clone(), &table, |_| {});
+        assert!(table.hashfull() > 0);
+        //the table is kept between searches so the same search needs fewer nodes
+        let second = position.search_with_table(limits.clone(), &table, |_| {});
+        assert!(second.nodes < first.nodes);
+        assert_eq!(second.depth, 4);
+
+        //the table can be shared by searches on several threads
+        let threads: Vec<_> = (0..2).map(|_| {
+            let (position, limits, table) = (position, limits.clone(), table.clone());
+            thread::spawn(move || position.search_with_table(limits, &table, |_| {}))
+        }).collect();
+        for thread in threads {
+            let result = thread.join().unwrap();
+            assert_eq!(result.depth, 4);
+            assert!(result.best_move.is_some());
+        }
+
+        //mate scores stay correct when they are found through the table
+        let position = Position::from_fen("6k1/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let table = TranspositionTable::new(1);
+        for _ in 0..2 {
+            let result = position.search_with_table(SearchLimits {depth: Some(4), ..Default::default()}, &table, |_| {});
+            assert_eq!(result.score, Score::Mate(2));
+        }
+    }
+
+}
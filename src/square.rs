@@ -0,0 +1,138 @@
+//typed squares, files and ranks for code using the library
+//inside the engine a square is a u64 with a single bit set, the index of that bit is the index of the square
+//h1 is 0, a1 is 7 and a8 is 63, the same order as SQUARE_NAME
+
+use std::fmt;
+
+use crate::SQUARE_NAME;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum File {
+    A, B, C, D, E, F, G, H
+}
+
+impl File {
+    pub const ALL: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+
+    //0 for the a-file and 7 for the h-file
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_index(index: u8) -> Option<File> {
+        File::ALL.get(index as usize).copied()
+    }
+
+    pub fn char(self) -> char {
+        (b'a' + self.index()) as char
+    }
+
+    pub fn from_char(letter: char) -> Option<File> {
+        File::ALL.into_iter().find(|file| file.char() == letter)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Rank {
+    First, Second, Third, Fourth, Fifth, Sixth, Seventh, Eighth
+}
+
+impl Rank {
+    pub const ALL: [Rank; 8] = [Rank::First, Rank::Second, Rank::Third, Rank::Fourth, Rank::Fifth, Rank::Sixth, Rank::Seventh, Rank::Eighth];
+
+    //0 for the first rank and 7 for the eighth rank
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_index(index: u8) -> Option<Rank> {
+        Rank::ALL.get(index as usize).copied()
+    }
+
+    pub fn char(self) -> char {
+        (b'1' + self.index()) as char
+    }
+
+    pub fn from_char(letter: char) -> Option<Rank> {
+        Rank::ALL.into_iter().find(|rank| rank.char() == letter)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    pub fn new(file: File, rank: Rank) -> Square {
+        Square(rank.index() * 8 + 7 - file.index())
+    }
+
+    //parses a square name like e4
+    pub fn from_name(name: &str) -> Option<Square> {
+        SQUARE_NAME.iter().position(|square_name| *square_name == name).map(|square| Square(square as u8))
+    }
+
+    pub fn name(self) -> &'static str {
+        SQUARE_NAME[self.0 as usize]
+    }
+
+    pub fn file(self) -> File {
+        File::ALL[7 - (self.0 % 8) as usize]
+    }
+
+    pub fn rank(self) -> Rank {
+        Rank::ALL[(self.0 / 8) as usize]
+    }
+
+    //the index of the bit in the engine's bitboards
+    pub(crate) fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    //the bitboard with only this square set
+    pub(crate) fn bitboard(self) -> u64 {
+        0b1u64 << self.0
+    }
+
+    //the bitboard has to have exactly one square set
+    pub(crate) fn from_bitboard(bitboard_square: u64) -> Square {
+        Square(bitboard_square.trailing_zeros() as u8)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+//squares are shown by name, e4 instead of Square(27)
+impl fmt::Debug for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{File, Rank, Square};
+
+    #[test]
+    fn square_names() {
+        let e4 = Square::new(File::E, Rank::Fourth);
+        assert_eq!(e4.name(), "e4");
+        assert_eq!(Square::from_name("e4"), Some(e4));
+        assert_eq!((e4.file(), e4.rank()), (File::E, Rank::Fourth));
+        assert_eq!(Square::from_name("i1"), None);
+        for file in File::ALL {
+            for rank in Rank::ALL {
+                let square = Square::new(file, rank);
+                assert_eq!(square.to_string(), format!("{}{}", file.char(), rank.char()));
+                assert_eq!(Square::from_bitboard(square.bitboard()), square);
+            }
+        }
+        assert_eq!(Square::new(File::H, Rank::First).bitboard(), 1);
+        assert_eq!(Square::new(File::A, Rank::Eighth).bitboard(), 1 << 63);
+        assert_eq!(File::from_char('c'), Some(File::C));
+        assert_eq!(Rank::from_index(8), None);
+    }
+}
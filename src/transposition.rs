@@ -0,0 +1,197 @@
+//transposition table that caches search results by zobrist key
+//https://www.chessprogramming.org/Transposition_Table
+//it can be shared between search threads without locks, see LocklessTable
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::{Move, Position, Role};
+use crate::lockless::LocklessTable;
+
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+const AGE_MASK: u8 = 0b111111;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Bound {
+    Exact,
+    Lower, //the score is at least this, the search failed high
+    Upper, //the score is at most this, the search failed low
+}
+
+//an entry as it is returned from a probe, the best move is only the squares and has to be found among the legal moves
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub depth: u8,
+    pub bound: Bound,
+    pub score: i16,
+    best_move: Option<(u8, u8, u8)>, //from square, destination square and promotion piece
+    age: u8,
+}
+
+impl Entry {
+    //the best move if it is legal in the position
+    pub fn best_move(&self, position: &Position) -> Option<Move> {
+        let (from, destination, promotion) = self.best_move?;
+        position.generate_moves().iter().find(|m| {
+            m.from.trailing_zeros() == from as u32 && m.destination.trailing_zeros() == destination as u32 && promotion_index(m.promotion) == promotion
+        }).copied()
+    }
+}
+
+pub struct TranspositionTable {
+    entries: LocklessTable,
+    age: AtomicU8, //incremented for every new search so old entries are replaced first
+}
+
+impl TranspositionTable {
+    //creates a table using at most size_mb megabytes, the number of entries is rounded down to a power of two
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        TranspositionTable {entries: LocklessTable::new(size_mb), age: AtomicU8::new(0)}
+    }
+
+    pub fn size_mb(&self) -> usize {
+        self.entries.size_mb()
+    }
+
+    //removes every entry, used between games
+    pub fn clear(&self) {
+        self.entries.clear();
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    //marks the entries of earlier searches as old so they are replaced before the new ones
+    pub fn new_search(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+
+    //how full the table is in permille, sent to uci guis as hashfull
+    pub fn hashfull(&self) -> u32 {
+        let age = self.age.load(Ordering::Relaxed) & AGE_MASK;
+        let count = self.entries.len().min(1000);
+        let used = (0..count).filter_map(|index| self.entries.load(index)).filter(|(_, data)| unpack(*data).age == age).count();
+        (used * 1000 / count) as u32
+    }
+
+    pub(crate) fn probe(&self, key: u64) -> Option<Entry> {
+        match self.entries.load(self.entries.index(key)) {
+            Some((stored_key, data)) if stored_key == key => Some(unpack(data)),
+            _ => None
+        }
+    }
+
+    //an entry is replaced when it is from an earlier search or when the new result is at least as deep
+    pub(crate) fn store(&self, key: u64, depth: u8, bound: Bound, score: i16, best_move: Option<&Move>) {
+        let age = self.age.load(Ordering::Relaxed) & AGE_MASK;
+        let index = self.entries.index(key);
+        let mut best_move = best_move.map(|m| (m.from.trailing_zeros() as u8, m.destination.trailing_zeros() as u8, promotion_index(m.promotion)));
+        if let Some((old_key, old_data)) = self.entries.load(index) {
+            let old = unpack(old_data);
+            let same_position = old_key == key;
+            if old.age == age && depth < old.depth && !(same_position && bound == Bound::Exact) {
+                return
+            }
+            //a search that found no best move keeps the move that was already known
+            if same_position && best_move.is_none() {
+                best_move = old.best_move;
+            }
+        }
+        let data = pack(&Entry {depth, bound, score, best_move, age});
+        self.entries.store(index, key, data);
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_SIZE_MB)
+    }
+}
+
+//0 when the move is not a promotion, a pawn never promotes to a king so 0 is free
+fn promotion_index(promotion: Option<Role>) -> u8 {
+    promotion.map_or(0, |role| role as u8)
+}
+
+//the data is packed in a u64, from the lowest bits:
+//score 16, depth 8, bound 2, age 6, has move 1, from 6, destination 6, promotion 3
+fn pack(entry: &Entry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    let mut data = entry.score as u16 as u64
+        | (entry.depth as u64) << 16
+        | bound << 24
+        | ((entry.age & AGE_MASK) as u64) << 26;
+    if let Some((from, destination, promotion)) = entry.best_move {
+        data |= 1 << 32 | (from as u64) << 33 | (destination as u64) << 39 | (promotion as u64) << 45;
+    }
+    data
+}
+
+fn unpack(data: u64) -> Entry {
+    let bound = match (data >> 24) & 0b11 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let best_move = if (data >> 32) & 1 == 1 {
+        Some((((data >> 33) & 0b111111) as u8, ((data >> 39) & 0b111111) as u8, ((data >> 45) & 0b111) as u8))
+    }
+    else {
+        None
+    };
+    Entry {
+        depth: (data >> 16) as u8,
+        bound,
+        score: data as u16 as i16,
+        best_move,
+        age: ((data >> 26) as u8) & AGE_MASK,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bound, TranspositionTable};
+    use crate::Position;
+
+    #[test]
+    fn store_and_probe() {
+        let table = TranspositionTable::new(1);
+        assert_eq!(table.size_mb(), 1);
+        let mut position = Position::startpos();
+        let key = position.hash();
+        assert_eq!(table.probe(key), None);
+
+        let m = *position.generate_moves().iter().find(|m| m.to_uci() == "e2e4").unwrap();
+        table.store(key, 5, Bound::Exact, -1234, Some(&m));
+        let entry = table.probe(key).unwrap();
+        assert_eq!((entry.depth, entry.bound, entry.score), (5, Bound::Exact, -1234));
+        assert_eq!(entry.best_move(&position), Some(m));
+        //the move is not legal in another position
+        position.make_move("g1f3");
+        assert_eq!(entry.best_move(&position), None);
+
+        //a shallower result of the same search does not replace a deeper one
+        table.store(key, 3, Bound::Lower, 50, None);
+        assert_eq!(table.probe(key).unwrap().depth, 5);
+        //but it does in a new search, the best move is kept
+        table.new_search();
+        table.store(key, 3, Bound::Upper, 50, None);
+        let entry = table.probe(key).unwrap();
+        assert_eq!((entry.depth, entry.bound, entry.score), (3, Bound::Upper, 50));
+        assert_eq!(entry.best_move(&Position::startpos()), Some(m));
+
+        //a key that maps to the same slot is not mistaken for the stored one
+        assert_eq!(table.probe(key ^ (1 << 63)), None);
+        table.clear();
+        assert_eq!(table.probe(key), None);
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(TranspositionTable::new(16).size_mb(), 16);
+        assert_eq!(TranspositionTable::new(20).size_mb(), 16);
+        assert_eq!(TranspositionTable::new(0).size_mb(), 1);
+    }
+}